@@ -1,4 +1,10 @@
 <?xml version="1.0" encoding="UTF-8"?>
 <tileset version="1.10" tiledversion="1.11.1-99-gec89c545" name="tile" tilewidth="255" tileheight="127" tilecount="1" columns="1">
  <image source="tile.png" width="255" height="127"/>
+ <tile id="0">
+  <properties>
+   <property name="cost" type="int" value="1"/>
+   <property name="walkable" type="bool" value="true"/>
+  </properties>
+ </tile>
 </tileset>
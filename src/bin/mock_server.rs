@@ -0,0 +1,32 @@
+use tinker::config::ClientConfig;
+use tinker::mock::MockServer;
+
+// runs the mock server on the configured host and port, e.g.
+//
+//      cargo run --features mock --bin mock_server -- --port 8080
+//
+fn main() {
+    let config = match ClientConfig::load() {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    };
+
+    let address = format!("{}:{}", config.host, config.port);
+
+    let server = match MockServer::start(&address) {
+        Ok(server) => server,
+        Err(error) => {
+            eprintln!("Could not bind {}: {}", address, error);
+            std::process::exit(1);
+        }
+    };
+
+    println!("Mock server listening on {}", server.address());
+
+    loop {
+        std::thread::park();
+    }
+}
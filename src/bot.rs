@@ -0,0 +1,228 @@
+use std::time::Duration;
+use bevy::prelude::*;
+use bevy::tasks::{block_on, poll_once};
+use rand::Rng;
+
+use crate::config::ClientConfig;
+use crate::errors::{Error, Result};
+use crate::player::{Player, PlayerType, Speed, Target};
+use crate::plugins::network::ConnectionStatus;
+use crate::plugins::pathfinding::ChooseTarget;
+use crate::queries::{self, AccountKey, Request};
+use crate::state::ConnectionState;
+use crate::views::ViewState;
+
+// settings for a scripted player, used by `--headless`
+#[derive(Resource, Debug, Clone)]
+pub struct BotConfig {
+    pub username: String,
+    pub password: String,
+    // seconds between picking new targets
+    pub interval: f32,
+    // targets are picked within this distance of the origin
+    pub range: f32,
+    // seconds to run before exiting, or forever if unset
+    pub duration: Option<f32>,
+}
+
+impl Default for BotConfig {
+    fn default() -> Self {
+        Self {
+            username: format!("bot-{:04}", rand::thread_rng().gen_range(0..10000)),
+            password: "bot-password-1".into(),
+            interval: 3.0,
+            range: 1000.0,
+            duration: None,
+        }
+    }
+}
+
+impl BotConfig {
+
+    // reads `--bot-username`, `--bot-password`, `--bot-interval`,
+    // `--bot-range` and `--duration` from the command line
+    pub fn from_args(args: &[String]) -> Result<Self> {
+        let mut config = Self::default();
+
+        if let Some(value) = ClientConfig::arg_value(args, "--bot-username")? {
+            config.username = value;
+        }
+        if let Some(value) = ClientConfig::arg_value(args, "--bot-password")? {
+            config.password = value;
+        }
+        if let Some(value) = ClientConfig::arg_value(args, "--bot-interval")? {
+            config.interval = Self::parse_positive("--bot-interval", &value)?;
+        }
+        if let Some(value) = ClientConfig::arg_value(args, "--bot-range")? {
+            config.range = Self::parse_positive("--bot-range", &value)?;
+        }
+        if let Some(value) = ClientConfig::arg_value(args, "--duration")? {
+            config.duration = Some(Self::parse_positive("--duration", &value)?);
+        }
+
+        Ok(config)
+    }
+
+    fn parse_positive(name: &str, value: &str) -> Result<f32> {
+        value
+            .trim()
+            .parse()
+            .ok()
+            .filter(|v: &f32| *v > 0.0)
+            .ok_or_else(|| Error::InvalidConfig(format!("'{}' is not a valid value for '{}'", value, name)))
+    }
+}
+
+// the in-flight register and login requests
+#[derive(Resource)]
+struct BotLogin(Request<Result<AccountKey>>);
+
+// counts down to the next random target
+#[derive(Resource)]
+struct WanderTimer(Timer);
+
+// logs in, then walks to random points until stopped
+pub struct BotPlugin;
+
+impl Plugin for BotPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Startup, login)
+            .add_systems(Update, poll_login.run_if(resource_exists::<BotLogin>))
+            .add_systems(OnEnter(ViewState::Game), spawn_player)
+            .add_systems(Update, wander
+                .in_set(ChooseTarget)
+                .run_if(in_state(ViewState::Game)))
+            .add_systems(Update, connection_status.run_if(resource_changed::<ConnectionStatus>))
+            .add_systems(Update, time_limit);
+    }
+}
+
+fn login(
+    mut commands: Commands,
+    bot: Res<BotConfig>,
+    config: Res<ClientConfig>,
+) {
+    let bot = bot.clone();
+    let config = config.clone();
+
+    info!("Bot '{}' logging in", bot.username);
+
+    let task = queries::spawn(async move {
+        // the account is kept between runs, so it may already exist
+        match queries::register(&config, &bot.username, &bot.password, &bot.password).await {
+            Ok(_) | Err(Error::UsernameTaken) => (),
+            Err(e) => return Err(e),
+        }
+        queries::login(&config, &bot.username, &bot.password).await
+    });
+
+    commands.insert_resource(BotLogin(task));
+}
+
+fn poll_login(
+    mut commands: Commands,
+    mut pending: ResMut<BotLogin>,
+    mut state: ResMut<ConnectionState>,
+    mut view_state: ResMut<NextState<ViewState>>,
+    mut app_exit_events: EventWriter<AppExit>,
+) {
+    let Some(result) = block_on(poll_once(&mut pending.0)) else {
+        return;
+    };
+
+    commands.remove_resource::<BotLogin>();
+
+    match result {
+        Ok(key) => {
+            info!("Bot '{}' logged in as {}", key.name, key.id);
+            state.id = key.id;
+            state.username = key.name;
+            state.token = Some(key.token);
+            view_state.set(ViewState::Game);
+        },
+        Err(e) => {
+            error!("Bot could not log in: {}", e);
+            app_exit_events.send(AppExit::error());
+        }
+    }
+}
+
+fn spawn_player(
+    mut commands: Commands,
+    bot: Res<BotConfig>,
+    state: Res<ConnectionState>,
+    asset_server: Res<AssetServer>,
+) {
+    Player::new::<PlayerType>(
+        state.id,
+        &asset_server,
+    )
+    .with_name(state.username.clone())
+    .build(&mut commands);
+
+    commands.insert_resource(WanderTimer(Timer::new(
+        Duration::from_secs_f32(bot.interval),
+        TimerMode::Repeating
+    )));
+}
+
+fn wander(
+    time: Res<Time>,
+    bot: Res<BotConfig>,
+    timer: Option<ResMut<WanderTimer>>,
+    mut query: Query<(&mut Speed, &mut Target), With<PlayerType>>,
+) {
+    let Some(mut timer) = timer else {
+        return;
+    };
+
+    if !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let mut rng = rand::thread_rng();
+
+    for (mut speed, mut target) in &mut query {
+        if target.0.is_none() {
+            speed.fixed = Some(speed.walking as f32);
+            target.0 = Some(Vec3::new(
+                rng.gen_range(-bot.range..bot.range),
+                rng.gen_range(-bot.range..bot.range),
+                0.0
+            ));
+        }
+    }
+}
+
+fn connection_status(
+    status: Res<ConnectionStatus>,
+    mut app_exit_events: EventWriter<AppExit>,
+) {
+    match *status {
+        ConnectionStatus::Connected => info!("Bot connected"),
+        ConnectionStatus::Reconnecting(attempt) => warn!("Bot reconnecting (attempt {})", attempt),
+        ConnectionStatus::Failed => {
+            error!("Bot lost its connection");
+            app_exit_events.send(AppExit::error());
+        },
+        ConnectionStatus::Closed => {
+            error!("Bot was disconnected by the server");
+            app_exit_events.send(AppExit::error());
+        },
+        ConnectionStatus::Connecting => (),
+    }
+}
+
+fn time_limit(
+    time: Res<Time>,
+    bot: Res<BotConfig>,
+    mut app_exit_events: EventWriter<AppExit>,
+) {
+    if let Some(duration) = bot.duration {
+        if time.elapsed_secs() >= duration {
+            info!("Bot finished after {}s", duration);
+            app_exit_events.send(AppExit::Success);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// longest message that will be sent, in characters
+pub const MAX_LENGTH: usize = 256;
+
+// who a message is for. Local chat only reaches characters near the
+// sender, and a whisper names the other player.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum Channel {
+    Global,
+    Local,
+    Whisper(String),
+}
+
+// a line of chat. `tinker_records` has no chat message, so these are
+// sent over the websocket next to its messages rather than inside them.
+// The server is expected to fill in the sender and echo the message
+// back to them like any other recipient.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChatMessage {
+    pub account_id: i32,
+    pub sender: String,
+    pub channel: Channel,
+    pub text: String,
+    pub sent: DateTime<Utc>,
+}
+
+impl ChatMessage {
+    pub fn new(account_id: i32, sender: &str, channel: Channel, text: &str) -> Self {
+        Self {
+            account_id,
+            sender: sender.to_string(),
+            channel,
+            text: text.chars().take(MAX_LENGTH).collect(),
+            sent: Utc::now(),
+        }
+    }
+}
+
+// split what was typed into a channel and the text to send. `/g`, `/l`
+// and `/w name` choose a channel, otherwise `current` is used. Returns
+// `None` if there is nothing to send.
+pub fn parse(input: &str, current: &Channel) -> Option<(Channel, String)> {
+    let input = input.trim();
+
+    let (channel, text) = match input.split_once(' ').unwrap_or((input, "")) {
+        ("/g", rest) => (Channel::Global, rest),
+        ("/l", rest) => (Channel::Local, rest),
+        ("/w", rest) => {
+            let (name, text) = rest.trim_start().split_once(' ')?;
+            (Channel::Whisper(name.to_string()), text)
+        },
+        _ => (current.clone(), input),
+    };
+
+    let text = text.trim();
+
+    if text.is_empty() {
+        None
+    } else {
+        Some((channel, text.to_string()))
+    }
+}
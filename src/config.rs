@@ -0,0 +1,230 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use bevy::ecs::system::Resource;
+use serde::{Serialize,Deserialize};
+use crate::errors::{Error, Result};
+
+const DEFAULT_CONFIG: &str = "tinker.toml";
+
+// settings used to reach the game server. Values are layered
+// with the following precedence (lowest to highest):
+//
+//      defaults < config file < environment < command line
+//
+// `scheme` is "http" or "https", and websockets use ws or wss to match.
+// Turning TLS on or off (`TINKER_TLS`, `--tls` and `--no-tls`) is a
+// shorthand for the scheme, and overrides one set alongside it.
+#[derive(Resource,Serialize,Deserialize,Debug,Clone)]
+#[serde(default)]
+pub struct ClientConfig {
+    pub host: String,
+    pub port: u16,
+    pub scheme: String,
+    pub prefix: String,
+    // seconds to wait for an HTTP response
+    pub timeout: u64,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            host: "localhost".into(),
+            port: 8080,
+            scheme: "http".into(),
+            prefix: String::new(),
+            timeout: 10,
+        }
+    }
+}
+
+impl ClientConfig {
+
+    pub fn load() -> Result<Self> {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        Self::load_from(&args, |name| std::env::var(name).ok())
+    }
+
+    // `load` with the arguments and environment passed in
+    pub fn load_from<F>(args: &[String], var: F) -> Result<Self>
+    where
+        F: Fn(&str) -> Option<String>
+    {
+        // the config file can be moved with `--config` or `TINKER_CONFIG`
+        let path = Self::arg_value(args, "--config")?
+            .or_else(|| var("TINKER_CONFIG"))
+            .map(PathBuf::from);
+
+        let mut config = match path {
+            Some(path) => Self::from_file(&path)?,
+            None => {
+                let path = PathBuf::from(DEFAULT_CONFIG);
+                if path.exists() {
+                    Self::from_file(&path)?
+                } else {
+                    Self::default()
+                }
+            }
+        };
+
+        config.apply_vars(var)?;
+        config.apply_args(args)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).map_err(Error::ConfigReadError)?;
+        Ok(toml::from_str(&text)?)
+    }
+
+    pub fn apply_env(&mut self) -> Result<()> {
+        self.apply_vars(|name| std::env::var(name).ok())
+    }
+
+    // `apply_env` with the variables looked up by `var`
+    pub fn apply_vars<F>(&mut self, var: F) -> Result<()>
+    where
+        F: Fn(&str) -> Option<String>
+    {
+        if let Some(value) = var("TINKER_HOST") {
+            self.host = value;
+        }
+        if let Some(value) = var("TINKER_PORT") {
+            self.port = Self::parse_port(&value)?;
+        }
+        if let Some(value) = var("TINKER_SCHEME") {
+            self.scheme = value;
+        }
+        if let Some(value) = var("TINKER_TLS") {
+            self.set_tls(Self::parse_bool(&value)?);
+        }
+        if let Some(value) = var("TINKER_PREFIX") {
+            self.prefix = value;
+        }
+        if let Some(value) = var("TINKER_TIMEOUT") {
+            self.timeout = Self::parse_timeout(&value)?;
+        }
+        Ok(())
+    }
+
+    pub fn apply_args(&mut self, args: &[String]) -> Result<()> {
+        if let Some(value) = Self::arg_value(args, "--host")? {
+            self.host = value;
+        }
+        if let Some(value) = Self::arg_value(args, "--port")? {
+            self.port = Self::parse_port(&value)?;
+        }
+        if let Some(value) = Self::arg_value(args, "--scheme")? {
+            self.scheme = value;
+        }
+        if let Some(value) = Self::arg_value(args, "--prefix")? {
+            self.prefix = value;
+        }
+        if let Some(value) = Self::arg_value(args, "--timeout")? {
+            self.timeout = Self::parse_timeout(&value)?;
+        }
+        // whichever of `--tls` and `--no-tls` comes last wins
+        for arg in args {
+            match arg.as_str() {
+                "--tls" => self.set_tls(true),
+                "--no-tls" => self.set_tls(false),
+                _ => ()
+            }
+        }
+        Ok(())
+    }
+
+    pub fn validate(&mut self) -> Result<()> {
+        self.host = self.host.trim().to_string();
+        self.scheme = self.scheme.trim().to_lowercase();
+        self.prefix = self.prefix.trim().trim_end_matches('/').to_string();
+
+        if self.host.is_empty() {
+            return Err(Error::InvalidConfig("host must not be empty".into()));
+        }
+
+        if self.host.contains('/') || self.host.contains(':') {
+            return Err(Error::InvalidConfig(format!("host '{}' must not include a scheme, port or path", self.host)));
+        }
+
+        if self.port == 0 {
+            return Err(Error::InvalidConfig("port must be between 1 and 65535".into()));
+        }
+
+        if self.scheme != "http" && self.scheme != "https" {
+            return Err(Error::InvalidConfig(format!("scheme '{}' must be 'http' or 'https'", self.scheme)));
+        }
+
+        if self.timeout == 0 {
+            return Err(Error::InvalidConfig("timeout must be at least one second".into()));
+        }
+
+        if !self.prefix.is_empty() && !self.prefix.starts_with('/') {
+            self.prefix = format!("/{}", self.prefix);
+        }
+
+        Ok(())
+    }
+
+    pub fn tls(&self) -> bool {
+        self.scheme == "https"
+    }
+
+    pub fn set_tls(&mut self, tls: bool) {
+        self.scheme = if tls { "https" } else { "http" }.into();
+    }
+
+    pub fn request_timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout)
+    }
+
+    // url for an HTTP endpoint, e.g. `http_url("/login")`
+    pub fn http_url(&self, path: &str) -> String {
+        format!("{}://{}:{}{}{}", self.scheme, self.host, self.port, self.prefix, path)
+    }
+
+    // url for a websocket endpoint, e.g. `ws_url("/connect/<token>")`
+    pub fn ws_url(&self, path: &str) -> String {
+        let scheme = if self.tls() { "wss" } else { "ws" };
+        format!("{}://{}:{}{}{}", scheme, self.host, self.port, self.prefix, path)
+    }
+
+    // finds `--name value` or `--name=value` in the argument list
+    pub(crate) fn arg_value(args: &[String], name: &str) -> Result<Option<String>> {
+        let prefix = format!("{}=", name);
+        for (i, arg) in args.iter().enumerate() {
+            if let Some(value) = arg.strip_prefix(&prefix) {
+                return Ok(Some(value.to_string()));
+            }
+            if arg == name {
+                return match args.get(i + 1) {
+                    Some(value) if !value.starts_with("--") => Ok(Some(value.clone())),
+                    _ => Err(Error::InvalidConfig(format!("missing value for '{}'", name)))
+                };
+            }
+        }
+        Ok(None)
+    }
+
+    fn parse_port(value: &str) -> Result<u16> {
+        value
+            .trim()
+            .parse()
+            .map_err(|_| Error::InvalidConfig(format!("port '{}' is not a valid number", value)))
+    }
+
+    fn parse_timeout(value: &str) -> Result<u64> {
+        value
+            .trim()
+            .parse()
+            .map_err(|_| Error::InvalidConfig(format!("timeout '{}' is not a valid number of seconds", value)))
+    }
+
+    fn parse_bool(value: &str) -> Result<bool> {
+        match value.trim().to_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => Ok(true),
+            "0" | "false" | "no" | "off" => Ok(false),
+            _ => Err(Error::InvalidConfig(format!("'{}' is not a valid boolean", value)))
+        }
+    }
+}
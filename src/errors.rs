@@ -5,15 +5,51 @@ pub enum Error {
     #[error("HTTP request failed")]
     HTTPRequestError(#[from] reqwest::Error),
 
-    #[error("Registration failed")]
-    RegisterFailed,
+    #[error("Registration failed: {0}")]
+    RegisterFailed(String),
 
-    #[error("Registration failed")]
-    LoginFailed,
+    #[error("Login failed: {0}")]
+    LoginFailed(String),
+
+    #[error("Username is already taken")]
+    UsernameTaken,
+
+    #[error("Passwords do not match")]
+    PasswordMismatch,
+
+    #[error("Incorrect username or password")]
+    BadCredentials,
+
+    #[error("Too many attempts, try again later")]
+    RateLimited,
+
+    #[error("Could not reach the server")]
+    ServerUnreachable,
+
+    #[error("{reason}")]
+    InvalidField {
+        field: String,
+        reason: String
+    },
 
     #[error("Could not [de]serialize data")]
     SerializationError(#[from] serde_json::Error),
 
     #[error("No character currently selected")]
     NoCharacter,
+
+    #[error("Could not read config file: {0}")]
+    ConfigReadError(std::io::Error),
+
+    #[error("File operation failed: {0}")]
+    FileError(#[from] std::io::Error),
+
+    #[error("Could not find a data directory")]
+    NoDataDirectory,
+
+    #[error("Could not parse config file: {0}")]
+    ConfigParseError(#[from] toml::de::Error),
+
+    #[error("Invalid config: {0}")]
+    InvalidConfig(String),
 }
@@ -0,0 +1,15 @@
+pub mod plugins;
+pub mod config;
+pub mod errors;
+pub mod player;
+pub mod cursor;
+pub mod queries;
+pub mod views;
+pub mod session;
+pub mod state;
+pub mod validation;
+pub mod bot;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod chat;
+pub mod stats;
@@ -1,20 +1,42 @@
-use std::sync::atomic::Ordering;
-use bevy::{prelude::*, window::WindowCloseRequested};
+use std::time::Duration;
+use bevy::app::ScheduleRunnerPlugin;
+use bevy::log::LogPlugin;
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
 use bevy_ecs_tiled::prelude::*;
 use bevy_ecs_tilemap::prelude::*;
 
-mod plugins;
-mod errors;
-mod player;
-mod cursor;
-mod queries;
-mod views;
-mod state;
+use tinker::{bot, plugins, session, views};
+use tinker::bot::BotConfig;
+use tinker::config::ClientConfig;
+use tinker::state::ConnectionState;
+use tinker::views::ViewState;
 
-use state::ConnectionState;
-use views::{game::{RUNNING, SHUTDOWN, SHUTDOWN_BARRIER}, ViewState};
+// how often the headless client runs its schedule
+const HEADLESS_TICK: Duration = Duration::from_micros(16_667);
+
+fn main() -> AppExit {
+    let config = match ClientConfig::load() {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    };
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if args.iter().any(|a| a == "--headless") {
+        let bot = match BotConfig::from_args(&args) {
+            Ok(bot) => bot,
+            Err(error) => {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+        };
+        return headless(config, bot);
+    }
 
-fn main() {
     App::new()
         .add_plugins(DefaultPlugins
             .set(WindowPlugin{
@@ -29,6 +51,7 @@ fn main() {
             .set(ImagePlugin::default_nearest()))
 
         .init_resource::<ConnectionState>()
+        .insert_resource(config)
 
         .add_plugins(TilemapPlugin)
         .add_plugins(TiledMapPlugin::default())
@@ -36,10 +59,34 @@ fn main() {
         .init_state::<ViewState>()
         .add_plugins(views::menu::main_menu)
         .add_plugins(views::game::main_game)
+        .add_plugins(plugins::network::NetworkPlugin)
+        .add_plugins(plugins::shutdown::ShutdownPlugin)
+        .add_plugins(session::SessionPlugin)
 
         .add_systems(Startup, setup)
-        .add_systems(PostUpdate, shutdown)
-        .run();
+        .run()
+}
+
+// runs the game logic without a window, driven by a bot
+fn headless(config: ClientConfig, bot: BotConfig) -> AppExit {
+    App::new()
+        .add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(HEADLESS_TICK)))
+        .add_plugins((LogPlugin::default(), StatesPlugin, AssetPlugin::default()))
+
+        // characters still carry sprites, which are loaded but never drawn
+        .add_plugins(ImagePlugin::default())
+        .init_asset::<TextureAtlasLayout>()
+
+        .init_resource::<ConnectionState>()
+        .insert_resource(config)
+        .insert_resource(bot)
+
+        .init_state::<ViewState>()
+        .add_plugins(views::game::game_logic)
+        .add_plugins(plugins::network::NetworkPlugin)
+        .add_plugins(plugins::shutdown::ShutdownPlugin)
+        .add_plugins(bot::BotPlugin)
+        .run()
 }
 
 fn setup(
@@ -49,18 +96,3 @@ fn setup(
 ) {
     commands.spawn(Camera2d);
 }
-
-fn shutdown(
-    mut exits: EventReader<AppExit>, 
-    mut closes: EventReader<WindowCloseRequested>
-) {
-    let exit_count = exits.read().count();
-    let close_count = closes.read().count();
-
-    if exit_count > 0 || close_count > 0 {
-        if RUNNING.load(Ordering::Relaxed) {
-            SHUTDOWN.store(true, Ordering::Relaxed);
-            SHUTDOWN_BARRIER.wait();
-        }
-    }
-}
\ No newline at end of file
@@ -0,0 +1,611 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use async_channel::Sender;
+use async_std::io::{ReadExt, WriteExt};
+use async_std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use async_std::task::{self, JoinHandle};
+use async_tungstenite::WebSocketStream;
+use bevy::log::{debug, warn};
+use bevy::math::Vec3;
+use futures_util::future::{select, Either};
+use futures_util::pin_mut;
+use futures_util::stream::StreamExt;
+use serde::Serialize;
+use tinker_records::messages::{
+    ConnectMessage,
+    DisconnectMessage,
+    EntityInfo,
+    Header,
+    InitialMessage,
+    Message,
+    Value
+};
+use tungstenite as ts;
+use tungstenite::protocol::Role;
+
+use crate::chat::{Channel, ChatMessage};
+use crate::config::ClientConfig;
+use crate::plugins::network::Packet;
+use crate::queries::{AccountInfo, AccountKey, ErrorResponse, LoginForm, RegisterForm};
+use crate::stats::{StatChange, StatMessage};
+
+// largest request head and body the server will read
+const MAX_HEAD: usize = 16 * 1024;
+const MAX_BODY: usize = 64 * 1024;
+
+// how far local chat carries, the same as the client's default
+const LOCAL_RANGE: f32 = 1500.;
+
+// one step of a scripted sequence sent to a client after it connects
+#[derive(Debug)]
+pub enum Step {
+    Send(Message),
+    Stats(StatMessage),
+    Wait(Duration),
+}
+
+// builds the script for a newly connected account
+type Script = Arc<dyn Fn(i32) -> Vec<Step> + Send + Sync>;
+
+struct Account {
+    id: i32,
+    username: String,
+    password: String,
+}
+
+#[derive(Default)]
+struct World {
+    accounts: HashMap<String, Account>,
+    tokens: HashMap<String, i32>,
+    clients: HashMap<i32, Sender<String>>,
+    positions: HashMap<i32, (f32, f32)>,
+    received: Vec<Message>,
+    next_id: i32,
+}
+
+#[derive(Default)]
+struct Shared {
+    world: Mutex<World>,
+    script: Mutex<Option<Script>>,
+}
+
+// an in-process stand-in for the game server. It implements the
+// `/register`, `/login`, `/validate` and `/connect/{token}` endpoints
+// closely enough to drive the client without the real backend.
+pub struct MockServer {
+    address: SocketAddr,
+    shared: Arc<Shared>,
+    task: Option<JoinHandle<()>>,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+struct Reply {
+    status: u16,
+    body: String,
+}
+
+impl MockServer {
+
+    // use port 0 to bind to an ephemeral port
+    pub async fn bind<A: ToSocketAddrs>(address: A) -> io::Result<Self> {
+        let listener = TcpListener::bind(address).await?;
+        let address = listener.local_addr()?;
+        let shared = Arc::new(Shared::default());
+        let task = task::spawn(serve(listener, shared.clone()));
+
+        Ok(Self {
+            address,
+            shared,
+            task: Some(task),
+        })
+    }
+
+    pub fn start(address: &str) -> io::Result<Self> {
+        task::block_on(Self::bind(address))
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    // a client config pointing at this server
+    pub fn config(&self) -> ClientConfig {
+        ClientConfig {
+            host: self.address.ip().to_string(),
+            port: self.address.port(),
+            ..Default::default()
+        }
+    }
+
+    // set the messages sent to every client after its `Initial` message
+    pub fn script<F>(&self, script: F)
+    where
+        F: Fn(i32) -> Vec<Step> + Send + Sync + 'static
+    {
+        *self.shared.script.lock().unwrap() = Some(Arc::new(script));
+    }
+
+    // create an account without going through `/register`
+    pub fn add_account(&self, username: &str, password: &str) -> i32 {
+        let mut world = self.shared.world.lock().unwrap();
+        world.create(username, password)
+    }
+
+    // send a message to every connected client
+    pub fn broadcast(&self, message: &Message) {
+        let world = self.shared.world.lock().unwrap();
+        world.broadcast(message, None);
+    }
+
+    // send a stat change to every connected client
+    pub fn broadcast_stats(&self, message: &StatMessage) {
+        let world = self.shared.world.lock().unwrap();
+        if let Ok(text) = serde_json::to_string(&Packet::Stats { stats: message.clone() }) {
+            world.relay(&text, None);
+        }
+    }
+
+    pub fn connected(&self) -> usize {
+        self.shared.world.lock().unwrap().clients.len()
+    }
+
+    // messages received from clients since the last call
+    pub fn take_received(&self) -> Vec<Message> {
+        std::mem::take(&mut self.shared.world.lock().unwrap().received)
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task::block_on(task.cancel());
+        }
+    }
+}
+
+impl World {
+    fn create(&mut self, username: &str, password: &str) -> i32 {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.accounts.insert(username.to_string(), Account {
+            id,
+            username: username.to_string(),
+            password: password.to_string(),
+        });
+        id
+    }
+
+    fn username(&self, id: i32) -> String {
+        self.accounts
+            .values()
+            .find(|a| a.id == id)
+            .map(|a| a.username.clone())
+            .unwrap_or_default()
+    }
+
+    fn entity(&self, id: i32) -> EntityInfo {
+        let (x, y) = self.positions.get(&id).cloned().unwrap_or_default();
+        EntityInfo {
+            id,
+            username: self.username(id),
+            x,
+            y,
+        }
+    }
+
+    fn broadcast(&self, message: &Message, except: Option<i32>) {
+        if let Ok(text) = serde_json::to_string(message) {
+            self.relay(&text, except);
+        }
+    }
+
+    // chat comes back from the server under the sender's real name,
+    // whispers only reach the two players involved, and local chat only
+    // reaches players within `LOCAL_RANGE`
+    fn chat(&self, id: i32, mut message: ChatMessage) {
+        message.account_id = id;
+        message.sender = self.username(id);
+
+        let Ok(text) = serde_json::to_string(&Packet::Chat { chat: message.clone() }) else {
+            return;
+        };
+
+        match &message.channel {
+            Channel::Whisper(name) => {
+                let recipient = self.accounts.get(name).map(|a| a.id);
+                for (client_id, client) in self.clients.iter() {
+                    if *client_id == id || Some(*client_id) == recipient {
+                        client.try_send(text.clone()).ok();
+                    }
+                }
+            },
+            Channel::Local => {
+                let (x, y) = self.positions.get(&id).cloned().unwrap_or_default();
+                for (client_id, client) in self.clients.iter() {
+                    let (cx, cy) = self.positions.get(client_id).cloned().unwrap_or_default();
+                    if (cx - x).hypot(cy - y) <= LOCAL_RANGE {
+                        client.try_send(text.clone()).ok();
+                    }
+                }
+            },
+            Channel::Global => self.relay(&text, None),
+        }
+    }
+
+    fn relay(&self, text: &str, except: Option<i32>) {
+        for (id, client) in self.clients.iter() {
+            if Some(*id) != except {
+                client.try_send(text.to_string()).ok();
+            }
+        }
+    }
+}
+
+impl Reply {
+    fn json<T: Serialize>(value: &T) -> Self {
+        Self {
+            status: 200,
+            body: serde_json::to_string(value).unwrap_or_default(),
+        }
+    }
+
+    fn error(status: u16, code: &str, field: Option<&str>, reason: &str) -> Self {
+        Self {
+            status,
+            body: serde_json::to_string(&ErrorResponse {
+                code: Some(code.into()),
+                field: field.map(String::from),
+                reason: Some(reason.into()),
+            }).unwrap_or_default(),
+        }
+    }
+}
+
+pub fn message(account_id: i32, value: Value) -> Message {
+    Message {
+        header: Header { account_id },
+        value,
+    }
+}
+
+pub fn initial(account_id: i32, entities: Vec<(i32, String, f32, f32)>) -> Message {
+    message(account_id, Value::Initial(InitialMessage {
+        entities: entities
+            .into_iter()
+            .map(|(id, username, x, y)| EntityInfo { id, username, x, y })
+            .collect()
+    }))
+}
+
+pub fn connect(account_id: i32, username: &str, x: f32, y: f32) -> Message {
+    message(account_id, Value::Connect(ConnectMessage {
+        entity: EntityInfo {
+            id: account_id,
+            username: username.to_string(),
+            x,
+            y,
+        }
+    }))
+}
+
+pub fn moving(account_id: i32, speed: f32, target: Vec3, position: Vec3) -> Message {
+    Message::Move(account_id, speed, target, position)
+}
+
+pub fn disconnect(account_id: i32) -> Message {
+    message(account_id, Value::Disconnect(DisconnectMessage {}))
+}
+
+pub fn stats(account_id: i32, change: StatChange) -> StatMessage {
+    StatMessage::new(account_id, change)
+}
+
+async fn serve(listener: TcpListener, shared: Arc<Shared>) {
+    let mut incoming = listener.incoming();
+    while let Some(stream) = incoming.next().await {
+        match stream {
+            Ok(stream) => {
+                task::spawn(handle(stream, shared.clone()));
+            },
+            Err(e) => warn!("Mock server failed to accept: {}", e)
+        }
+    }
+}
+
+async fn handle(mut stream: TcpStream, shared: Arc<Shared>) {
+    let request = match read_request(&mut stream).await {
+        Ok(request) => request,
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+            let reply = Reply::error(413, "too_large", None, "Request too large");
+            write_reply(&mut stream, &reply).await.ok();
+            return;
+        },
+        Err(_) => return,
+    };
+
+    let path = request.path
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .to_string();
+
+    debug!("Mock server: {} {}", request.method, path);
+
+    let reply = match (request.method.as_str(), path.as_str()) {
+        ("POST", "/register") => register(&shared, &request),
+        ("GET", "/login") => login(&shared, &request),
+        ("GET", "/validate") => validate(&shared, &request),
+        ("GET", path) if path.starts_with("/connect/") => {
+            let token = path.trim_start_matches("/connect/").to_string();
+            return socket(stream, shared, request, token).await;
+        },
+        _ => Reply::error(404, "not_found", None, "Not found")
+    };
+
+    write_reply(&mut stream, &reply).await.ok();
+}
+
+fn register(shared: &Shared, request: &Request) -> Reply {
+    let Ok(form) = serde_json::from_slice::<RegisterForm>(&request.body) else {
+        return Reply::error(400, "bad_request", None, "Malformed registration");
+    };
+
+    if form.password1 != form.password2 {
+        return Reply::error(400, "password_mismatch", Some("password2"), "Passwords do not match");
+    }
+
+    let mut world = shared.world.lock().unwrap();
+
+    if world.accounts.contains_key(&form.username) {
+        return Reply::error(409, "username_taken", Some("username"), "Username is already taken");
+    }
+
+    let id = world.create(&form.username, &form.password1);
+    Reply::json(&AccountInfo {
+        id,
+        username: form.username,
+    })
+}
+
+fn login(shared: &Shared, request: &Request) -> Reply {
+    let Ok(form) = serde_json::from_slice::<LoginForm>(&request.body) else {
+        return Reply::error(400, "bad_request", None, "Malformed login");
+    };
+
+    let mut world = shared.world.lock().unwrap();
+
+    let id = match world.accounts.get(&form.username) {
+        Some(account) if account.password == form.password => account.id,
+        _ => return Reply::error(401, "bad_credentials", None, "Incorrect username or password")
+    };
+
+    let token = format!("mock-{}-{}", id, world.tokens.len());
+    world.tokens.insert(token.clone(), id);
+
+    Reply::json(&AccountKey {
+        id,
+        name: form.username,
+        token,
+    })
+}
+
+fn validate(shared: &Shared, request: &Request) -> Reply {
+    let token = request.headers
+        .get("authorization")
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or_default();
+
+    let world = shared.world.lock().unwrap();
+
+    match world.tokens.get(token) {
+        Some(id) => Reply::json(&AccountInfo {
+            id: *id,
+            username: world.username(*id),
+        }),
+        None => Reply::error(401, "bad_credentials", None, "Unknown session")
+    }
+}
+
+async fn socket(mut stream: TcpStream, shared: Arc<Shared>, request: Request, token: String) {
+    let id = shared.world.lock().unwrap().tokens.get(&token).cloned();
+
+    let Some(id) = id else {
+        let reply = Reply::error(401, "bad_credentials", None, "Unknown session");
+        write_reply(&mut stream, &reply).await.ok();
+        return;
+    };
+
+    let Some(key) = request.headers.get("sec-websocket-key") else {
+        let reply = Reply::error(400, "bad_request", None, "Expected a websocket upgrade");
+        write_reply(&mut stream, &reply).await.ok();
+        return;
+    };
+
+    let accept = ts::handshake::derive_accept_key(key.as_bytes());
+    let head = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept
+    );
+
+    if stream.write_all(head.as_bytes()).await.is_err() {
+        return;
+    }
+
+    let mut socket = WebSocketStream::from_raw_socket(stream, Role::Server, None).await;
+    let (outbox, inbox) = async_channel::unbounded::<String>();
+
+    // the new client gets everyone else, everyone else gets the new client
+    {
+        let mut world = shared.world.lock().unwrap();
+
+        let others = world.clients
+            .keys()
+            .filter(|k| **k != id)
+            .map(|k| world.entity(*k))
+            .map(|e| (e.id, e.username, e.x, e.y))
+            .collect();
+
+        if let Ok(text) = serde_json::to_string(&initial(id, others)) {
+            outbox.try_send(text).ok();
+        }
+
+        let entity = world.entity(id);
+        world.broadcast(&connect(id, &entity.username, entity.x, entity.y), Some(id));
+        world.clients.insert(id, outbox.clone());
+    }
+
+    let script = shared.script.lock().unwrap().clone();
+    if let Some(script) = script {
+        task::spawn(run_script(script(id), outbox.clone()));
+    }
+
+    loop {
+        let source = socket.next();
+        let sink = inbox.recv();
+
+        pin_mut!(sink);
+
+        match select(source, sink).await {
+            Either::Left((Some(Ok(ts::Message::Text(text))), _)) => {
+                let message = match serde_json::from_str::<Packet>(text.as_str()) {
+                    Ok(Packet::Record(message)) => message,
+                    Ok(Packet::Chat { chat }) => {
+                        shared.world.lock().unwrap().chat(id, chat);
+                        continue;
+                    },
+                    // only the server sends stats
+                    Ok(Packet::Stats { .. }) | Err(_) => continue,
+                };
+
+                let mut world = shared.world.lock().unwrap();
+
+                if let Value::Move(value) = &message.value {
+                    world.positions.insert(id, (value.position.x, value.position.y));
+                    world.relay(text.as_str(), Some(id));
+                }
+
+                world.received.push(message);
+            },
+            Either::Left((Some(Ok(ts::Message::Close(_))) | Some(Err(_)) | None, _)) => break,
+            Either::Left(_) => (),
+            Either::Right((Ok(text), _)) => {
+                if socket.send(ts::Message::text(text)).await.is_err() {
+                    break;
+                }
+            },
+            Either::Right((Err(_), _)) => break,
+        }
+    }
+
+    // a reconnect for the same account replaces this connection, and
+    // that player is still online
+    let mut world = shared.world.lock().unwrap();
+    if world.clients.get(&id).is_some_and(|client| client.same_channel(&outbox)) {
+        world.clients.remove(&id);
+        world.broadcast(&disconnect(id), None);
+    }
+}
+
+async fn run_script(steps: Vec<Step>, outbox: Sender<String>) {
+    for step in steps {
+        match step {
+            Step::Send(message) => {
+                let Ok(text) = serde_json::to_string(&message) else {
+                    continue;
+                };
+                if outbox.send(text).await.is_err() {
+                    return;
+                }
+            },
+            Step::Stats(stats) => {
+                let Ok(text) = serde_json::to_string(&Packet::Stats { stats }) else {
+                    continue;
+                };
+                if outbox.send(text).await.is_err() {
+                    return;
+                }
+            },
+            Step::Wait(duration) => task::sleep(duration).await,
+        }
+    }
+}
+
+async fn read_request(stream: &mut TcpStream) -> io::Result<Request> {
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() > MAX_HEAD {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "request head too large"));
+        }
+        if stream.read(&mut byte).await? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        head.push(byte[0]);
+    }
+
+    let head = String::from_utf8_lossy(&head);
+    let mut lines = head.split("\r\n");
+    let mut start = lines.next().unwrap_or_default().split_whitespace();
+
+    let method = start.next().unwrap_or_default().to_string();
+    let path = start.next().unwrap_or_default().to_string();
+
+    let headers: HashMap<String, String> = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| (k.trim().to_lowercase(), v.trim().to_string()))
+        .collect();
+
+    let length = headers
+        .get("content-length")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+
+    // the length is only trusted up to a limit
+    if length > MAX_BODY {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "request body too large"));
+    }
+
+    let mut body = vec![0u8; length];
+    stream.read_exact(&mut body).await?;
+
+    Ok(Request {
+        method,
+        path,
+        headers,
+        body,
+    })
+}
+
+async fn write_reply(stream: &mut TcpStream, reply: &Reply) -> io::Result<()> {
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        reply.status,
+        reason(reply.status),
+        reply.body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(reply.body.as_bytes()).await?;
+    stream.flush().await
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        _ => "Error"
+    }
+}
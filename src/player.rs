@@ -1,7 +1,12 @@
 use bevy::prelude::*;
+use serde::Deserialize;
 use std::time::Duration;
+use crate::plugins::animation::AnimationSet;
 use crate::views::game::OnGame;
 
+// the animations every character uses until the server says otherwise
+pub const DEFAULT_ANIMATIONS: &str = "animations/character.anim.ron";
+
 // marker for all entities (current player or others)
 #[derive(Component, Default)]
 pub struct EntityType;
@@ -14,35 +19,59 @@ pub struct PlayerType;
 #[derive(Component, Default)]
 pub struct CharacterType;
 
-#[derive(Component, Clone, Copy)]
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Direction {
-    TopLeft,
+    Top,
     TopRight,
+    Right,
+    BotRight,
+    Bot,
     BotLeft,
-    BotRight
+    Left,
+    TopLeft,
 }
 
 impl Direction {
+    // every direction, anticlockwise from straight right
+    pub const ALL: [Direction; 8] = [
+        Self::Right,
+        Self::TopRight,
+        Self::Top,
+        Self::TopLeft,
+        Self::Left,
+        Self::BotLeft,
+        Self::Bot,
+        Self::BotRight,
+    ];
+
+    // the closest of the eight directions to the angle of `vector`, each
+    // covering 45 degrees. A zero vector faces right.
     pub fn from(vector: &Vec3) -> Self {
-        let x = vector.x;
-        let y = vector.y;
+        let angle = vector.y.atan2(vector.x).rem_euclid(std::f32::consts::TAU);
+        let sector = (angle / std::f32::consts::FRAC_PI_4).round() as usize;
+        Self::ALL[sector % Self::ALL.len()]
+    }
 
-        if x >= 0. && y >= 0. {
-            Self::TopRight
-        }
-        else if x < 0. && y >= 0. {
-            Self::TopLeft
-        }
-        else if x < 0. && y < 0. {
-            Self::BotLeft
-        }
-        else if x >= 0. && y < 0. {
-            Self::BotRight
-        } 
-        else {
-            unreachable!();
+    // the direction reflected left to right
+    pub fn mirror(self) -> Self {
+        match self {
+            Self::Top => Self::Top,
+            Self::TopRight => Self::TopLeft,
+            Self::Right => Self::Left,
+            Self::BotRight => Self::BotLeft,
+            Self::Bot => Self::Bot,
+            Self::BotLeft => Self::BotRight,
+            Self::Left => Self::Right,
+            Self::TopLeft => Self::TopRight,
         }
     }
+
+    // the two directions either side of this one
+    fn neighbours(self) -> [Self; 2] {
+        let index = Self::ALL.iter().position(|d| *d == self).unwrap_or_default();
+        let count = Self::ALL.len();
+        [Self::ALL[(index + 1) % count], Self::ALL[(index + count - 1) % count]]
+    }
 }
 
 #[derive(Bundle)]
@@ -62,6 +91,7 @@ where T: Sync + Send + Component + Default
     sprite: Sprite,
     target: Target,
     direction: Direction,
+    position: Position,
     transform: Transform,
     marker: OnGame,
 }
@@ -71,16 +101,9 @@ impl Player
     pub fn new<T>(
         id: i32,
         assets: &Res<AssetServer>,
-        atlas: &mut ResMut<Assets<TextureAtlasLayout>>
     ) -> Player<T> 
         where T: Sync + Send + Component + Default
     {
-
-        let path = "sprites/character2.png";
-        let texture: Handle<Image> = assets.load(path);
-        let layout = TextureAtlasLayout::from_grid(UVec2::new(255,512), 6, 3, None, None);
-        let handle = atlas.add(layout);
-
         Player {
             id: AccountId(id),
 
@@ -90,7 +113,8 @@ impl Player
             name: Name(String::new()),
             experience: Experience { 
                 current: 0, 
-                level: 1 
+                level: 1,
+                required: None
             },
             health: Health {
                 current: 100,
@@ -101,38 +125,12 @@ impl Player
                 running: 6,
                 fixed: None
             },
-            graphic: Graphic {
-                idle: Animation {
-                    topleft: vec![2],
-                    topright: vec![3],
-                    botleft: vec![0],
-                    botright: vec![1],
-                },
-                running: Animation {
-                    topleft: vec![8,14],
-                    topright: vec![9,15],
-                    botleft: vec![6,12],
-                    botright: vec![7,13],
-                },
-                walking: Animation {
-                    topleft: vec![8,14],
-                    topright: vec![9,15],
-                    botleft: vec![6,12],
-                    botright: vec![7,13],
-                },
-                fps: 5.,
-                timer: Graphic::timer(5.)
-            },
-            sprite: Sprite {
-                image: texture.clone(),
-                texture_atlas: Some(TextureAtlas {
-                    layout: handle.clone(),
-                    index: 1,
-                }),
-                ..default()
-            },
+            // the sheet is filled in once the animation set has loaded
+            graphic: Graphic::new(assets.load(DEFAULT_ANIMATIONS)),
+            sprite: Sprite::default(),
             target: Target(None),
             direction: Direction::BotRight,
+            position: Position::new(Vec3::new(0., 0., 2.)),
             transform: Transform::from_xyz(0., 0., 2.),
             marker: OnGame
         }
@@ -144,6 +142,7 @@ where
     T: Sync + Send + Component + Default
 {
     pub fn with_position(mut self, x: f32, y: f32, z: f32) -> Self {
+        self.position = Position::new(Vec3::new(x, y, z));
         self.transform = Transform::from_xyz(x, y, z);
         self
     }
@@ -158,19 +157,10 @@ where
         self
     }
 
+    // the name and health bar above the character are added by the
+    // nameplate plugin, so headless clients don't draw them
     pub fn build(self, commands: &mut Commands) {
-        let name = self.name.0.clone();
-        commands
-            .spawn(self)
-            .with_child((
-                Text2d::new(name),
-                TextFont {
-                    font_size: 50.0,
-                    ..default()
-                },
-                Transform::from_translation(Vec3::new(0.0, 260.0, 1.0)),
-                TextLayout::new_with_justify(JustifyText::Center),
-            ));
+        commands.spawn(self);
     }
 
 }
@@ -181,12 +171,34 @@ pub struct AccountId(pub i32);
 #[derive(Component, Debug)]
 pub struct Name(String);
 
+impl Name {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
 #[derive(Component, Debug)]
 pub struct Experience {
     pub current: usize,
     pub level: usize,
+    // experience needed for the next level, which is only known once
+    // the server has sent the character's level
+    pub required: Option<usize>,
 }
 
+impl Experience {
+    // how far through the current level, from 0 to 1
+    pub fn progress(&self) -> f32 {
+        match self.required {
+            Some(required) if required > 0 => (self.current as f32 / required as f32).clamp(0., 1.),
+            _ => 0.,
+        }
+    }
+}
+
+// `walking` and `running` are speed tiers, and `fixed` is the tier the
+// character is currently moving at. Each tier is `UNITS_PER_TIER` world
+// units per second, so walking at 2 covers 200 units a second.
 #[derive(Component, Debug)]
 pub struct Speed {
     pub walking: usize,
@@ -194,58 +206,171 @@ pub struct Speed {
     pub fixed: Option<f32>
 }
 
+impl Speed {
+    pub const UNITS_PER_TIER: f32 = 100.;
+
+    // world units per second for a speed tier
+    pub fn units_per_second(tier: f32) -> f32 {
+        tier * Self::UNITS_PER_TIER
+    }
+}
+
 #[derive(Component, Debug)]
 pub struct Health {
     pub current: usize,
     pub maximum: usize,
 }
 
+impl Health {
+    // the fraction of health left, from 0 to 1
+    pub fn fraction(&self) -> f32 {
+        (self.current as f32 / self.maximum.max(1) as f32).clamp(0., 1.)
+    }
+}
+
 #[derive(Component)]
 pub struct Target(pub Option<Vec3>);
 
+// where the character is in the simulation, which only moves on the
+// fixed tick. `Transform` is drawn between `previous` and `current`.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Position {
+    pub previous: Vec3,
+    pub current: Vec3,
+}
+
+impl Position {
+    pub fn new(position: Vec3) -> Self {
+        Self {
+            previous: position,
+            current: position,
+        }
+    }
+
+    // move somewhere without drawing the path in between
+    pub fn teleport(&mut self, position: Vec3) {
+        *self = Self::new(position);
+    }
+}
+
+// which clip of an animation set a character is playing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Motion {
+    Idle,
+    Walking,
+    Running,
+}
+
 #[derive(Component, Debug)]
 pub struct Graphic {
-    pub idle: Animation,
-    pub running: Animation,
-    pub walking: Animation,
-    pub fps: f32,
+    pub set: Handle<AnimationSet>,
+    pub motion: Motion,
     pub timer: Timer,
 }
 
 impl Graphic {
-    pub fn reset(&mut self) {
-        self.timer = Self::timer(self.fps);
+    pub fn new(set: Handle<AnimationSet>) -> Self {
+        Self {
+            set,
+            motion: Motion::Idle,
+            timer: Self::timer(5.),
+        }
+    }
+
+    pub fn reset(&mut self, fps: f32) {
+        self.timer = Self::timer(fps);
     }
 
     fn timer(fps: f32) -> Timer {
         Timer::new(
-            Duration::from_secs_f32(1.0 / fps), 
+            Duration::from_secs_f32(1.0 / fps.max(0.1)), 
             TimerMode::Once
         )
     }
 }
 
-#[derive(Component, Debug)]
+// frame indices into the sprite sheet for each direction. A sheet
+// doesn't need all eight: a missing direction uses the frames of its
+// mirror image flipped, or failing that the nearest direction that has
+// frames. Misspelled directions are an error rather than a missing
+// direction.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
 pub struct Animation {
-    topleft: Vec<usize>,
+    top: Vec<usize>,
     topright: Vec<usize>,
-    botleft: Vec<usize>,
+    right: Vec<usize>,
     botright: Vec<usize>,
+    bot: Vec<usize>,
+    botleft: Vec<usize>,
+    left: Vec<usize>,
+    topleft: Vec<usize>,
 }
 
 impl Animation {
 
-    pub fn facing(&self, direction: Direction) -> &Vec<usize> {
+    fn frames(&self, direction: Direction) -> &Vec<usize> {
         match direction {
-            Direction::TopLeft => &self.topleft,
+            Direction::Top => &self.top,
             Direction::TopRight => &self.topright,
-            Direction::BotLeft => &self.botleft,
+            Direction::Right => &self.right,
             Direction::BotRight => &self.botright,
+            Direction::Bot => &self.bot,
+            Direction::BotLeft => &self.botleft,
+            Direction::Left => &self.left,
+            Direction::TopLeft => &self.topleft,
         }
     }
+
+    // every frame used by any direction
+    pub fn indices(&self) -> impl Iterator<Item = usize> + '_ {
+        Direction::ALL
+            .into_iter()
+            .flat_map(|direction| self.frames(direction).iter().copied())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices().next().is_none()
+    }
+
+    // the frames to show for `direction`, and whether they have to be
+    // flipped horizontally
+    pub fn facing(&self, direction: Direction) -> (&Vec<usize>, bool) {
+        let [a, b] = direction.neighbours();
+
+        let candidates = [
+            (direction, false),
+            (direction.mirror(), true),
+            (a, false),
+            (b, false),
+            (a.mirror(), true),
+            (b.mirror(), true),
+        ];
+
+        candidates
+            .into_iter()
+            .map(|(d, flip)| (self.frames(d), flip && d != direction))
+            .find(|(frames, _)| !frames.is_empty())
+            .unwrap_or((self.frames(direction), false))
+    }
+
+    pub fn flipped(&self, direction: Direction) -> bool {
+        self.facing(direction).1
+    }
     
-    pub fn next(&self, direction: Direction, current: usize) -> usize {
-        let animation = self.facing(direction);
+    pub fn first(&self, direction: Direction) -> Option<usize> {
+        self.facing(direction).0.first().cloned()
+    }
+
+    // the frame after `current`, starting over from the first frame if
+    // `current` isn't part of this animation. Animations that don't loop
+    // stay on their last frame.
+    pub fn next(&self, direction: Direction, current: usize, looping: bool) -> usize {
+        let (animation, _) = self.facing(direction);
+
+        if !looping && animation.last() == Some(&current) {
+            return current;
+        }
 
         let value = animation
             .iter()
@@ -0,0 +1,238 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::player::{Animation, Direction, EntityType, Graphic, Motion, Speed, Target};
+
+// a character's sprite sheet and the clips cut from it, loaded from
+// `*.anim.ron` files under `assets/`. For example:
+//
+//      (
+//          sheet: "sprites/character2.png",
+//          cell: (255, 512),
+//          columns: 6,
+//          rows: 3,
+//          idle: (fps: 5.0, frames: (topleft: [2], topright: [3], botleft: [0], botright: [1])),
+//          walking: ( ... ),
+//          running: ( ... ),
+//      )
+//
+#[derive(Asset, TypePath, Debug)]
+pub struct AnimationSet {
+    pub image: Handle<Image>,
+    pub layout: Handle<TextureAtlasLayout>,
+    pub idle: Clip,
+    pub walking: Clip,
+    pub running: Clip,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Clip {
+    pub fps: f32,
+    #[serde(default = "looping")]
+    pub looping: bool,
+    pub frames: Animation,
+}
+
+// the file as written, before the sheet is loaded
+#[derive(Deserialize, Debug)]
+pub struct AnimationFile {
+    pub sheet: String,
+    pub cell: (u32, u32),
+    pub columns: u32,
+    pub rows: u32,
+    pub idle: Clip,
+    pub walking: Clip,
+    pub running: Clip,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum AnimationSetError {
+    #[error("Could not read animation set")]
+    Io(#[from] std::io::Error),
+
+    #[error("Could not parse animation set: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+
+    #[error("Invalid animation set: {0}")]
+    Invalid(String),
+}
+
+#[derive(Default)]
+pub struct AnimationSetLoader;
+
+pub struct AnimationSetPlugin;
+
+impl Plugin for AnimationSetPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_asset::<AnimationSet>()
+            .init_asset_loader::<AnimationSetLoader>()
+            .add_systems(Update, (apply_animation_sets, animate_characters).chain());
+    }
+}
+
+fn looping() -> bool {
+    true
+}
+
+impl AnimationSet {
+    pub fn clip(&self, motion: Motion) -> &Clip {
+        match motion {
+            Motion::Idle => &self.idle,
+            Motion::Walking => &self.walking,
+            Motion::Running => &self.running,
+        }
+    }
+}
+
+impl AnimationFile {
+
+    // reads a set and checks that every clip can be played from the sheet
+    pub fn parse(bytes: &[u8]) -> Result<Self, AnimationSetError> {
+        let file: Self = ron::de::from_bytes(bytes)?;
+        let cells = (file.columns * file.rows) as usize;
+
+        for (name, clip) in [("idle", &file.idle), ("walking", &file.walking), ("running", &file.running)] {
+            if clip.fps.is_nan() || clip.fps <= 0. {
+                return Err(AnimationSetError::Invalid(format!("{} has an fps of {}", name, clip.fps)));
+            }
+
+            if clip.frames.is_empty() {
+                return Err(AnimationSetError::Invalid(format!("{} has no frames", name)));
+            }
+
+            if let Some(frame) = clip.frames.indices().find(|&frame| frame >= cells) {
+                return Err(AnimationSetError::Invalid(format!(
+                    "{} uses frame {} but the sheet only has {}", name, frame, cells)));
+            }
+        }
+
+        Ok(file)
+    }
+}
+
+impl AssetLoader for AnimationSetLoader {
+    type Asset = AnimationSet;
+    type Settings = ();
+    type Error = AnimationSetError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+
+        let file = AnimationFile::parse(&bytes)?;
+
+        let layout = TextureAtlasLayout::from_grid(
+            UVec2::new(file.cell.0, file.cell.1),
+            file.columns,
+            file.rows,
+            None,
+            None
+        );
+
+        Ok(AnimationSet {
+            image: context.load(file.sheet),
+            layout: context.add_labeled_asset("layout".into(), layout),
+            idle: file.idle,
+            walking: file.walking,
+            running: file.running,
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["anim.ron"]
+    }
+}
+
+// puts the sheet on characters once their set has loaded, and again
+// whenever the file is changed on disk (with the `dev` feature)
+fn apply_animation_sets(
+    mut events: EventReader<AssetEvent<AnimationSet>>,
+    sets: Res<Assets<AnimationSet>>,
+    mut query: Query<(Ref<Graphic>, &Direction, &mut Sprite)>,
+) {
+    let changed: Vec<AssetId<AnimationSet>> = events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    for (graphic, direction, mut sprite) in &mut query {
+        if !graphic.is_added() && !changed.contains(&graphic.set.id()) {
+            continue;
+        }
+
+        let Some(set) = sets.get(&graphic.set) else {
+            continue;
+        };
+
+        let frames = &set.clip(graphic.motion).frames;
+        let index = frames.first(*direction).unwrap_or_default();
+
+        sprite.image = set.image.clone();
+        sprite.flip_x = frames.flipped(*direction);
+        sprite.texture_atlas = Some(TextureAtlas {
+            layout: set.layout.clone(),
+            index,
+        });
+    }
+}
+
+// characters moving at their running tier or faster use the running
+// clip, and anything slower walks.
+fn animate_characters(
+    time: Res<Time>,
+    sets: Res<Assets<AnimationSet>>,
+    mut query: Query<(&mut Graphic, &mut Sprite, &Speed, &Target, &Direction), With<EntityType>>,
+) {
+    for (mut graphic, mut sprite, speed, target, direction) in &mut query {
+        let Some(set) = sets.get(&graphic.set) else {
+            continue;
+        };
+
+        let motion = match (target.0, speed.fixed) {
+            (None, _) => Motion::Idle,
+            (Some(_), Some(tier)) if tier >= speed.running as f32 => Motion::Running,
+            (Some(_), _) => Motion::Walking,
+        };
+
+        let clip = set.clip(motion);
+        let (frames, flip) = clip.frames.facing(*direction);
+
+        if sprite.flip_x != flip {
+            sprite.flip_x = flip;
+        }
+
+        let turned = sprite.texture_atlas
+            .as_ref()
+            .is_some_and(|atlas| !frames.contains(&atlas.index));
+
+        // turning or switching clips shows the new frames straight away
+        if motion != graphic.motion || turned {
+            graphic.motion = motion;
+            graphic.reset(clip.fps);
+            if let Some(atlas) = &mut sprite.texture_atlas {
+                atlas.index = frames.first().cloned().unwrap_or(atlas.index);
+            }
+            continue;
+        }
+
+        graphic.timer.tick(time.delta());
+
+        if graphic.timer.just_finished() {
+            if let Some(atlas) = &mut sprite.texture_atlas {
+                atlas.index = clip.frames.next(*direction, atlas.index, clip.looping);
+            }
+            graphic.reset(clip.fps);
+        }
+    }
+}
@@ -11,6 +11,10 @@ where
     pub action: T
 }
 
+// marks a button that should ignore presses
+#[derive(Component,Default)]
+pub struct Disabled;
+
 #[derive(Bundle,Default)]
 pub struct MyButtonLabel {
     text: Text,
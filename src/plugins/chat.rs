@@ -0,0 +1,431 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+use bevy::prelude::*;
+use bevy::ui::FocusPolicy;
+use bevy_simple_text_input::{
+    TextInput,
+    TextInputInactive,
+    TextInputPlaceholder,
+    TextInputSettings,
+    TextInputSubmitEvent,
+    TextInputSystem,
+    TextInputTextColor,
+    TextInputTextFont,
+    TextInputValue
+};
+use chrono::Local;
+
+use crate::chat::{self, Channel, ChatMessage};
+use crate::player::{AccountId, EntityType, PlayerType};
+use crate::plugins::nameplate::Nameplate;
+use crate::plugins::network::{IncomingChat, OutgoingChat};
+use crate::plugins::selection::PickingSet;
+use crate::state::ConnectionState;
+use crate::views::game::OnGame;
+use crate::views::ViewState;
+
+const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+const LOCAL_COLOR: Color = Color::srgb(1.0, 0.95, 0.7);
+const WHISPER_COLOR: Color = Color::srgb(1.0, 0.6, 0.9);
+const BORDER_COLOR_ACTIVE: Color = Color::srgb(1.0, 0.84, 0.0);
+const BORDER_COLOR_INACTIVE: Color = Color::srgba(0.0, 0.0, 0.0, 0.0);
+
+// where speech bubbles sit above a character, just above the nameplate
+const BUBBLE_HEIGHT: f32 = 340.;
+
+// how much chat is kept and shown, how far local chat carries and how
+// long speech bubbles stay up
+#[derive(Resource, Debug, Clone)]
+pub struct ChatSettings {
+    pub history: usize,
+    pub visible_lines: usize,
+    pub local_range: f32,
+    pub bubble_duration: Duration,
+}
+
+impl Default for ChatSettings {
+    fn default() -> Self {
+        Self {
+            history: 200,
+            visible_lines: 10,
+            local_range: 1500.,
+            bubble_duration: Duration::from_secs(5),
+        }
+    }
+}
+
+// every message received, oldest first, and how many lines back from
+// the newest the log has been scrolled
+#[derive(Resource, Debug, Default)]
+pub struct ChatHistory {
+    pub messages: VecDeque<ChatMessage>,
+    pub scroll: usize,
+}
+
+// the channel used when a message doesn't pick one
+#[derive(Resource, Debug)]
+pub struct ChatChannel(pub Channel);
+
+impl Default for ChatChannel {
+    fn default() -> Self {
+        Self(Channel::Local)
+    }
+}
+
+#[derive(Component)]
+struct ChatLog;
+
+#[derive(Component)]
+pub struct ChatInput;
+
+#[derive(Component)]
+struct SpeechBubble(Timer);
+
+pub struct ChatPlugin;
+
+impl Plugin for ChatPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<ChatSettings>()
+            .init_resource::<ChatHistory>()
+            .init_resource::<ChatChannel>()
+            .add_systems(OnEnter(ViewState::Game), setup_chat)
+            .add_systems(OnExit(ViewState::Game), clear_chat)
+            .add_systems(Update, (focus_chat, submit_chat)
+                .chain()
+                .after(TextInputSystem)
+                .before(PickingSet)
+                .run_if(in_state(ViewState::Game)))
+            .add_systems(Update, (
+                receive_chat,
+                scroll_chat,
+                show_chat.run_if(resource_changed::<ChatHistory>),
+                expire_bubbles
+            )
+                .chain()
+                .run_if(in_state(ViewState::Game)));
+    }
+}
+
+impl ChatHistory {
+    fn push(&mut self, message: ChatMessage, limit: usize) {
+        if self.messages.len() >= limit {
+            self.messages.pop_front();
+        }
+        self.messages.push_back(message);
+
+        // stay on the same lines while reading back through the log
+        if self.scroll > 0 {
+            self.scroll = (self.scroll + 1).min(self.messages.len().saturating_sub(1));
+        }
+    }
+}
+
+fn setup_chat(mut commands: Commands) {
+    let panel = (
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.0),
+            left: Val::Px(10.0),
+            width: Val::Px(420.0),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(5.0),
+            padding: UiRect::all(Val::Px(5.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
+        OnGame
+    );
+
+    let log = (
+        Node {
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::End,
+            min_height: Val::Px(180.0),
+            overflow: Overflow::clip(),
+            ..default()
+        },
+        ChatLog
+    );
+
+    let input = (
+        Node {
+            width: Val::Percent(100.0),
+            border: UiRect::all(Val::Px(2.0)),
+            padding: UiRect::all(Val::Px(5.0)),
+            ..default()
+        },
+        BorderColor(BORDER_COLOR_INACTIVE),
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
+        TextInputValue("".to_string()),
+        Interaction::default(),
+        FocusPolicy::Block,
+        TextInput,
+        TextInputSettings {
+            retain_on_submit: false,
+            ..default()
+        },
+        TextInputTextFont(TextFont {
+            font_size: 16.,
+            ..default()
+        }),
+        TextInputTextColor(TextColor(TEXT_COLOR)),
+        TextInputPlaceholder {
+            value: "Press Enter to chat".to_string(),
+            ..default()
+        },
+        TextInputInactive(true),
+        ChatInput
+    );
+
+    commands
+        .spawn(panel)
+        .with_children(|parent| {
+            parent.spawn(log);
+            parent.spawn(input);
+        });
+}
+
+fn clear_chat(mut history: ResMut<ChatHistory>, mut channel: ResMut<ChatChannel>) {
+    *history = ChatHistory::default();
+    *channel = ChatChannel::default();
+}
+
+// keys typed into the chat box aren't meant for the game
+pub fn chat_inactive(query: Query<&TextInputInactive, With<ChatInput>>) -> bool {
+    query.iter().all(|inactive| inactive.0)
+}
+
+// Enter starts typing, and clicking anywhere else stops
+fn focus_chat(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut buttons: ResMut<ButtonInput<MouseButton>>,
+    mut query: Query<(&Interaction, &mut TextInputInactive, &mut BorderColor), With<ChatInput>>,
+) {
+    for (interaction, mut inactive, mut border) in &mut query {
+        let activate = (inactive.0 && keys.just_pressed(KeyCode::Enter))
+            || *interaction == Interaction::Pressed;
+        let deactivate = !inactive.0
+            && buttons.just_pressed(MouseButton::Left)
+            && *interaction == Interaction::None;
+
+        if *interaction == Interaction::Pressed {
+            buttons.reset(MouseButton::Left);
+        }
+
+        if activate {
+            inactive.0 = false;
+            *border = BORDER_COLOR_ACTIVE.into();
+        } else if deactivate {
+            inactive.0 = true;
+            *border = BORDER_COLOR_INACTIVE.into();
+        }
+    }
+}
+
+// sending a message also closes the chat box, so Enter toggles it
+fn submit_chat(
+    mut events: EventReader<TextInputSubmitEvent>,
+    mut query: Query<(&mut TextInputInactive, &mut BorderColor), With<ChatInput>>,
+    mut channel: ResMut<ChatChannel>,
+    mut history: ResMut<ChatHistory>,
+    mut outgoing: EventWriter<OutgoingChat>,
+    state: Res<ConnectionState>,
+) {
+    for event in events.read() {
+        let Ok((mut inactive, mut border)) = query.get_mut(event.entity) else {
+            continue;
+        };
+
+        inactive.0 = true;
+        *border = BORDER_COLOR_INACTIVE.into();
+
+        let Some((target, text)) = chat::parse(&event.value, &channel.0) else {
+            continue;
+        };
+
+        // whispers go back to the current channel afterwards
+        if !matches!(target, Channel::Whisper(_)) {
+            channel.0 = target.clone();
+        }
+
+        history.scroll = 0;
+        outgoing.send(OutgoingChat(ChatMessage::new(
+            state.id,
+            &state.username,
+            target,
+            &text
+        )));
+    }
+}
+
+// everything goes in the log, and anything said locally also appears
+// over the speaker's head. The bubble hangs off the nameplate so it is
+// scaled with it when the camera zooms.
+#[allow(clippy::too_many_arguments)]
+fn receive_chat(
+    mut commands: Commands,
+    mut events: EventReader<IncomingChat>,
+    mut history: ResMut<ChatHistory>,
+    settings: Res<ChatSettings>,
+    speakers: Query<(Entity, &AccountId, &GlobalTransform, Option<&Children>), With<EntityType>>,
+    player: Query<&GlobalTransform, With<PlayerType>>,
+    plates: Query<(&Transform, Option<&Children>), With<Nameplate>>,
+    bubbles: Query<(), With<SpeechBubble>>,
+) {
+    for IncomingChat(message) in events.read() {
+        history.push(message.clone(), settings.history);
+
+        if message.channel != Channel::Local {
+            continue;
+        }
+
+        // the server decides who hears local chat, so a speaker that
+        // isn't here or is out of range only goes without a bubble
+        let speaker = speakers
+            .iter()
+            .find(|(_, id, _, _)| id.0 == message.account_id);
+
+        let Some((entity, _, transform, children)) = speaker else {
+            continue;
+        };
+
+        let near = player
+            .get_single()
+            .map(|p| p.translation().distance(transform.translation()) <= settings.local_range)
+            .unwrap_or(true);
+
+        if !near {
+            continue;
+        }
+
+        let plate = children
+            .into_iter()
+            .flatten()
+            .find_map(|child| plates.get(*child).ok().map(|p| (*child, p)));
+
+        let (parent, height, children) = match plate {
+            Some((plate, (transform, children))) => (plate, BUBBLE_HEIGHT - transform.translation.y, children),
+            None => (entity, BUBBLE_HEIGHT, children),
+        };
+
+        // a new bubble replaces the last one
+        for child in children.into_iter().flatten() {
+            if bubbles.contains(*child) {
+                commands.entity(*child).despawn_recursive();
+            }
+        }
+
+        commands.entity(parent).with_children(|parent| {
+            spawn_bubble(parent, &message.text, height, settings.bubble_duration);
+        });
+    }
+}
+
+fn spawn_bubble(parent: &mut ChildBuilder<'_>, text: &str, height: f32, duration: Duration) {
+    let font_size = 40.0;
+    let width = (text.chars().count() as f32 * font_size * 0.55).min(900.) + 40.;
+    let lines = (text.chars().count() as f32 * font_size * 0.55 / 900.).ceil().max(1.);
+
+    parent
+        .spawn((
+            Sprite {
+                color: Color::srgba(1.0, 1.0, 1.0, 0.85),
+                custom_size: Some(Vec2::new(width, lines * font_size * 1.3 + 20.)),
+                ..default()
+            },
+            Transform::from_translation(Vec3::new(0.0, height, 2.0)),
+            SpeechBubble(Timer::new(duration, TimerMode::Once)),
+        ))
+        .with_child((
+            Text2d::new(text),
+            TextFont {
+                font_size,
+                ..default()
+            },
+            TextColor(Color::BLACK),
+            TextLayout::new_with_justify(JustifyText::Center),
+            bevy::text::TextBounds::new_horizontal(900.),
+            Transform::from_translation(Vec3::new(0.0, 0.0, 0.1)),
+        ));
+}
+
+fn expire_bubbles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut SpeechBubble)>,
+) {
+    for (entity, mut bubble) in &mut query {
+        if bubble.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+// Page Up and Page Down move back through the log
+fn scroll_chat(
+    keys: Res<ButtonInput<KeyCode>>,
+    settings: Res<ChatSettings>,
+    mut history: ResMut<ChatHistory>,
+) {
+    let page = settings.visible_lines.saturating_sub(1).max(1);
+    let oldest = history.messages.len().saturating_sub(settings.visible_lines);
+
+    if keys.just_pressed(KeyCode::PageUp) {
+        history.scroll = (history.scroll + page).min(oldest);
+    }
+
+    if keys.just_pressed(KeyCode::PageDown) {
+        history.scroll = history.scroll.saturating_sub(page);
+    }
+}
+
+fn show_chat(
+    mut commands: Commands,
+    history: Res<ChatHistory>,
+    settings: Res<ChatSettings>,
+    state: Res<ConnectionState>,
+    query: Query<Entity, With<ChatLog>>,
+) {
+    let Ok(log) = query.get_single() else {
+        return;
+    };
+
+    let end = history.messages.len().saturating_sub(history.scroll);
+    let start = end.saturating_sub(settings.visible_lines);
+
+    commands.entity(log).despawn_descendants();
+    commands.entity(log).with_children(|parent| {
+        for message in history.messages.range(start..end) {
+            parent.spawn((
+                Text::new(format_line(message, &state.username)),
+                TextFont {
+                    font_size: 16.0,
+                    ..default()
+                },
+                TextColor(line_color(&message.channel)),
+            ));
+        }
+    });
+}
+
+fn format_line(message: &ChatMessage, username: &str) -> String {
+    let time = message.sent.with_timezone(&Local).format("%H:%M");
+
+    match &message.channel {
+        Channel::Global => format!("[{}] [Global] {}: {}", time, message.sender, message.text),
+        Channel::Local => format!("[{}] {}: {}", time, message.sender, message.text),
+        Channel::Whisper(to) if message.sender == username => {
+            format!("[{}] To {}: {}", time, to, message.text)
+        },
+        Channel::Whisper(_) => format!("[{}] From {}: {}", time, message.sender, message.text),
+    }
+}
+
+fn line_color(channel: &Channel) -> Color {
+    match channel {
+        Channel::Global => TEXT_COLOR,
+        Channel::Local => LOCAL_COLOR,
+        Channel::Whisper(_) => WHISPER_COLOR,
+    }
+}
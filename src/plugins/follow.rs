@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+
+use crate::player::{CharacterType, PlayerType, Position, Speed, Target};
+use crate::plugins::pathfinding::ChooseTarget;
+use crate::views::ViewState;
+
+// how close to stay to a character being followed, and how far they can
+// move before the route to them is worked out again
+#[derive(Resource, Debug, Clone)]
+pub struct FollowSettings {
+    pub distance: f32,
+    pub retarget: f32,
+}
+
+impl Default for FollowSettings {
+    fn default() -> Self {
+        Self {
+            distance: 150.,
+            retarget: 50.,
+        }
+    }
+}
+
+// the local player keeps walking after another character
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Follow(pub Entity);
+
+pub struct FollowPlugin;
+
+impl Plugin for FollowPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<FollowSettings>()
+            .add_systems(Update, follow
+                .in_set(ChooseTarget)
+                .run_if(in_state(ViewState::Game)));
+    }
+}
+
+// movement is sent by the scheduler like any other, so other clients
+// just see the player walking
+fn follow(
+    mut commands: Commands,
+    settings: Res<FollowSettings>,
+    leaders: Query<&Transform, With<CharacterType>>,
+    mut query: Query<(Entity, &Follow, &Position, &mut Speed, &mut Target), With<PlayerType>>,
+) {
+    for (entity, follow, position, mut speed, mut target) in &mut query {
+        let Ok(leader) = leaders.get(follow.0) else {
+            // they logged out or were despawned
+            commands.entity(entity).remove::<Follow>();
+            continue;
+        };
+
+        let leader = leader.translation.with_z(position.current.z);
+        let distance = position.current.distance(leader);
+
+        if distance <= settings.distance {
+            if target.0.is_some() {
+                target.0 = None;
+            }
+            continue;
+        }
+
+        let stale = target.0.is_none_or(|point| point.with_z(leader.z).distance(leader) > settings.retarget);
+
+        if stale {
+            if speed.fixed.is_none() {
+                speed.fixed = Some(speed.walking as f32);
+            }
+            target.0 = Some(leader);
+        }
+    }
+}
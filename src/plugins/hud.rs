@@ -0,0 +1,205 @@
+use bevy::prelude::*;
+use bevy::window::{PrimaryWindow, WindowResized};
+
+use crate::player::{self, Experience, Health, PlayerType};
+use crate::views::game::OnGame;
+use crate::views::ViewState;
+
+const HEALTH_COLOR: Color = Color::srgb(0.8, 0.1, 0.1);
+const HEALTH_BACKGROUND: Color = Color::srgb(0.3, 0.05, 0.05);
+const EXPERIENCE_COLOR: Color = Color::srgb(0.55, 0.3, 0.9);
+const EXPERIENCE_BACKGROUND: Color = Color::srgb(0.15, 0.08, 0.25);
+const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+
+// text is sized as a fraction of the window height, within these bounds
+const FONT_SCALE: f32 = 0.022;
+const FONT_MIN: f32 = 12.;
+const FONT_MAX: f32 = 28.;
+
+#[derive(Component)]
+struct Hud;
+
+// each line of text in the HUD
+#[derive(Component, Clone, Copy, PartialEq)]
+enum HudText {
+    Name,
+    Level,
+    Health,
+    Experience,
+}
+
+// the filled part of each bar
+#[derive(Component, Clone, Copy, PartialEq)]
+enum HudBar {
+    Health,
+    Experience,
+}
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(ViewState::Game), setup_hud)
+            .add_systems(Update, (
+                update_name,
+                update_health,
+                update_experience,
+                scale_hud
+            ).run_if(in_state(ViewState::Game)));
+    }
+}
+
+fn setup_hud(mut commands: Commands) {
+    let root = (
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            width: Val::Vw(22.0),
+            min_width: Val::Px(180.0),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Vh(0.6),
+            padding: UiRect::all(Val::Vh(1.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
+        Hud,
+        OnGame
+    );
+
+    let font = TextFont {
+        font_size: 16.0,
+        ..default()
+    };
+
+    commands
+        .spawn(root)
+        .with_children(|parent| {
+            parent
+                .spawn(Node {
+                    justify_content: JustifyContent::SpaceBetween,
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((Text::new(""), font.clone(), TextColor(TEXT_COLOR), HudText::Name));
+                    parent.spawn((Text::new(""), font.clone(), TextColor(TEXT_COLOR), HudText::Level));
+                });
+
+            bar(parent, HudBar::Health, HEALTH_COLOR, HEALTH_BACKGROUND);
+            parent.spawn((Text::new(""), font.clone(), TextColor(TEXT_COLOR), HudText::Health));
+
+            bar(parent, HudBar::Experience, EXPERIENCE_COLOR, EXPERIENCE_BACKGROUND);
+            parent.spawn((Text::new(""), font, TextColor(TEXT_COLOR), HudText::Experience));
+        });
+}
+
+fn bar(parent: &mut ChildBuilder<'_>, kind: HudBar, color: Color, background: Color) {
+    parent
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Vh(1.5),
+                min_height: Val::Px(8.0),
+                ..default()
+            },
+            BackgroundColor(background),
+        ))
+        .with_child((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            BackgroundColor(color),
+            kind,
+        ));
+}
+
+// the HUD is spawned alongside the player, so `Changed` is also true on
+// the first frame and fills everything in
+fn update_name(
+    player: Query<&player::Name, (With<PlayerType>, Changed<player::Name>)>,
+    mut texts: Query<(&HudText, &mut Text)>,
+) {
+    let Ok(name) = player.get_single() else {
+        return;
+    };
+
+    for (kind, mut text) in &mut texts {
+        if *kind == HudText::Name {
+            text.0 = name.as_str().into();
+        }
+    }
+}
+
+fn update_health(
+    player: Query<&Health, (With<PlayerType>, Changed<Health>)>,
+    mut texts: Query<(&HudText, &mut Text)>,
+    mut bars: Query<(&HudBar, &mut Node)>,
+) {
+    let Ok(health) = player.get_single() else {
+        return;
+    };
+
+    for (kind, mut text) in &mut texts {
+        if *kind == HudText::Health {
+            text.0 = format!("{} / {}", health.current, health.maximum);
+        }
+    }
+
+    for (kind, mut node) in &mut bars {
+        if *kind == HudBar::Health {
+            node.width = Val::Percent(health.fraction() * 100.);
+        }
+    }
+}
+
+fn update_experience(
+    player: Query<&Experience, (With<PlayerType>, Changed<Experience>)>,
+    mut texts: Query<(&HudText, &mut Text)>,
+    mut bars: Query<(&HudBar, &mut Node)>,
+) {
+    let Ok(experience) = player.get_single() else {
+        return;
+    };
+
+    for (kind, mut text) in &mut texts {
+        match kind {
+            HudText::Level => text.0 = format!("Level {}", experience.level),
+            HudText::Experience => text.0 = match experience.required {
+                Some(required) => format!("{} / {} XP", experience.current, required),
+                None => format!("{} XP", experience.current),
+            },
+            _ => (),
+        }
+    }
+
+    for (kind, mut node) in &mut bars {
+        if *kind == HudBar::Experience {
+            node.width = Val::Percent(experience.progress() * 100.);
+        }
+    }
+}
+
+// node sizes follow the window on their own, but text has to be resized
+fn scale_hud(
+    mut resized: EventReader<WindowResized>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    added: Query<(), Added<Hud>>,
+    mut texts: Query<&mut TextFont, With<HudText>>,
+) {
+    if resized.read().count() == 0 && added.is_empty() {
+        return;
+    }
+
+    let Ok(window) = window.get_single() else {
+        return;
+    };
+
+    let size = (window.height() * FONT_SCALE).clamp(FONT_MIN, FONT_MAX);
+
+    for mut font in &mut texts {
+        font.font_size = size;
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+use bevy::prelude::*;
+
+use crate::player::{CharacterType, Direction, Speed, Target};
+use crate::views::ViewState;
+
+// most snapshots kept for a single character
+const BUFFER_CAPACITY: usize = 32;
+
+// remote characters are drawn `delay` behind the newest snapshot, and
+// may keep moving on their own for `max_extrapolation` if packets are late
+#[derive(Resource, Debug, Clone)]
+pub struct InterpolationSettings {
+    pub delay: Duration,
+    pub max_extrapolation: Duration,
+}
+
+impl Default for InterpolationSettings {
+    fn default() -> Self {
+        Self {
+            delay: Duration::from_millis(100),
+            max_extrapolation: Duration::from_millis(250),
+        }
+    }
+}
+
+// a remote character's position as reported by a `Move` message. The
+// protocol has no server clock, so `time` is when the message arrived.
+// `speed` is the tier the character is moving at.
+#[derive(Debug, Clone, Copy)]
+pub struct Snapshot {
+    pub time: f64,
+    pub position: Vec3,
+    pub target: Vec3,
+    pub speed: f32,
+}
+
+// recent snapshots for a remote character, oldest first
+#[derive(Component, Debug, Default)]
+pub struct SnapshotBuffer {
+    snapshots: VecDeque<Snapshot>,
+}
+
+pub struct InterpolationPlugin;
+
+impl Plugin for InterpolationPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<InterpolationSettings>()
+            .add_systems(Update, interpolate.run_if(in_state(ViewState::Game)));
+    }
+}
+
+impl SnapshotBuffer {
+
+    // remote characters start with a single snapshot where they spawned
+    pub fn starting_at(time: f64, position: Vec3) -> Self {
+        let mut buffer = Self::default();
+        buffer.push(Snapshot {
+            time,
+            position,
+            target: position,
+            speed: 0.,
+        });
+        buffer
+    }
+
+    pub fn push(&mut self, snapshot: Snapshot) {
+        if self.snapshots.len() >= BUFFER_CAPACITY {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(snapshot);
+    }
+
+    pub fn latest(&self) -> Option<&Snapshot> {
+        self.snapshots.back()
+    }
+
+    // the position and velocity of the character at `time`
+    pub fn sample(&mut self, time: f64, settings: &InterpolationSettings) -> Option<(Vec3, Vec3)> {
+        let delay = settings.delay.as_secs_f64();
+        let limit = settings.max_extrapolation.as_secs_f64();
+
+        // only the last snapshot before `time` is needed, but keep two
+        // around so there is always a velocity to extrapolate with
+        while self.snapshots.len() > 2 && self.snapshots[1].time <= time {
+            self.snapshots.pop_front();
+        }
+
+        let last = *self.snapshots.back()?;
+
+        if let Some(index) = self.snapshots.iter().position(|s| s.time > time) {
+            let next = self.snapshots[index];
+
+            let Some(prev) = index.checked_sub(1).map(|i| self.snapshots[i]) else {
+                return Some((next.position, Vec3::ZERO));
+            };
+
+            let (start, velocity) = Self::segment(&prev, &next, delay);
+
+            if time <= start {
+                return Some((prev.position, Vec3::ZERO));
+            }
+
+            let t = ((time - start) / (next.time - start)) as f32;
+            return Some((prev.position.lerp(next.position, t), velocity));
+        }
+
+        let Some(prev) = self.snapshots.len().checked_sub(2).map(|i| self.snapshots[i]) else {
+            return Some((last.position, Vec3::ZERO));
+        };
+
+        // the packets are late, so carry on toward the last target
+        let (_, velocity) = Self::segment(&prev, &last, delay);
+        let elapsed = (time - last.time).min(limit);
+        let step = velocity * elapsed as f32;
+        let remaining = last.target - last.position;
+
+        let position = if step.length() >= remaining.length() {
+            last.target
+        } else {
+            last.position + step
+        };
+
+        if elapsed >= limit || position == last.target {
+            // rest here so the next snapshot starts from where the
+            // character was drawn rather than where it was last seen
+            if let Some(back) = self.snapshots.back_mut() {
+                back.position = position;
+                back.target = position;
+            }
+            return Some((position, Vec3::ZERO));
+        }
+
+        Some((position, velocity))
+    }
+
+    // when a character starts moving after standing still, the gap
+    // between snapshots is crossed in `delay` instead of crawling across it
+    fn segment(prev: &Snapshot, next: &Snapshot, delay: f64) -> (f64, Vec3) {
+        let start = prev.time.max(next.time - delay);
+        let span = (next.time - start).max(f64::EPSILON);
+        (start, (next.position - prev.position) / span as f32)
+    }
+}
+
+fn interpolate(
+    time: Res<Time<Real>>,
+    settings: Res<InterpolationSettings>,
+    mut query: Query<(
+        &mut SnapshotBuffer,
+        &mut Transform,
+        &mut Target,
+        &mut Speed,
+        &mut Direction
+    ), With<CharacterType>>,
+) {
+    let render = time.elapsed_secs_f64() - settings.delay.as_secs_f64();
+
+    for (mut buffer, mut transform, mut target, mut speed, mut facing) in &mut query {
+        let Some((position, velocity)) = buffer.sample(render, &settings) else {
+            continue;
+        };
+
+        if transform.translation.truncate() != position.truncate() {
+            transform.translation.x = position.x;
+            transform.translation.y = position.y;
+        }
+
+        // animations only look at whether there is a target, and the
+        // tier picks between the walking and running clips
+        if velocity.length_squared() > 0.0 {
+            if let Some(latest) = buffer.latest() {
+                target.0 = Some(latest.target);
+                if speed.fixed != Some(latest.speed) {
+                    speed.fixed = Some(latest.speed);
+                }
+            }
+            *facing = Direction::from(&velocity);
+        } else {
+            target.0 = None;
+        }
+    }
+}
@@ -1,2 +1,15 @@
 
-pub mod button;
\ No newline at end of file
+pub mod animation;
+pub mod button;
+pub mod chat;
+pub mod follow;
+pub mod hud;
+pub mod interpolation;
+pub mod nameplate;
+pub mod movement;
+pub mod network;
+pub mod pathfinding;
+pub mod prediction;
+pub mod scheduler;
+pub mod selection;
+pub mod shutdown;
\ No newline at end of file
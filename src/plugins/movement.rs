@@ -0,0 +1,114 @@
+use bevy::app::RunFixedMainLoopSystem;
+use bevy::prelude::*;
+
+use crate::player::{AccountId, Direction, Position, Speed, Target};
+use crate::plugins::interpolation::SnapshotBuffer;
+use crate::plugins::pathfinding::Path;
+use crate::plugins::prediction::Prediction;
+use crate::state::ConnectionState;
+use crate::views::ViewState;
+
+// how many times a second movement is simulated, whatever the frame rate
+pub const SIMULATION_RATE: f64 = 60.;
+
+// local movement is simulated before anything else in the tick that
+// depends on where the player is
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SimulateMovement;
+
+pub struct MovementPlugin;
+
+impl Plugin for MovementPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(Time::<Fixed>::from_hz(SIMULATION_RATE))
+            .add_systems(FixedUpdate, character_movement
+                .in_set(SimulateMovement)
+                .run_if(in_state(ViewState::Game)))
+            .add_systems(RunFixedMainLoop, smooth_movement
+                .in_set(RunFixedMainLoopSystem::AfterFixedMainLoop)
+                .run_if(in_state(ViewState::Game)));
+    }
+}
+
+// move `position` toward `target` at `speed` units per second, landing
+// exactly on the target rather than passing it
+pub fn step(position: Vec3, target: Vec3, speed: f32, delta: f32) -> Vec3 {
+    let target = Vec3::new(target.x, target.y, position.z);
+    let remaining = target - position;
+    let amount = speed * delta;
+
+    if remaining.length() <= amount {
+        target
+    } else {
+        position + remaining.normalize() * amount
+    }
+}
+
+// remote characters are moved by their snapshots instead
+#[allow(clippy::type_complexity)]
+fn character_movement(
+    time: Res<Time>,
+    mut query: Query<(
+        &AccountId,
+        &mut Position,
+        &Speed,
+        &mut Target,
+        &mut Direction,
+        Option<&mut Path>
+    ), Without<SnapshotBuffer>>,
+    state: Res<ConnectionState>,
+    mut prediction: ResMut<Prediction>,
+) {
+    let delta = time.delta_secs();
+
+    for (id, mut position, speed, mut target, mut facing, mut path) in &mut query {
+        position.previous = position.current;
+
+        let (Some(point), Some(tier)) = (target.0, speed.fixed) else {
+            continue;
+        };
+
+        // walk the path if there is one, otherwise straight to the target
+        let point = path.as_ref().and_then(|p| p.next()).unwrap_or(point);
+
+        let cpos = position.current;
+        let tpos = Vec3::new(point.x, point.y, cpos.z);
+        let units = Speed::units_per_second(tier);
+
+        if tpos != cpos {
+            // update the facing direction of the character
+            *facing = Direction::from(&(tpos - cpos));
+        }
+
+        position.current = step(cpos, tpos, units, delta);
+
+        if id.0 == state.id {
+            // kept until the server answers, in case it disagrees
+            prediction.record(position.current, tpos, units, delta);
+        }
+
+        if position.current == tpos {
+            let remaining = path.as_mut().is_some_and(|p| p.advance());
+            if !remaining {
+                target.0 = None;
+            }
+        }
+    }
+}
+
+// draw characters part of the way to their next tick, so movement looks
+// smooth when the frame rate and the simulation rate don't line up
+fn smooth_movement(
+    time: Res<Time<Fixed>>,
+    mut query: Query<(&Position, &mut Transform), Without<SnapshotBuffer>>,
+) {
+    let fraction = time.overstep_fraction();
+
+    for (position, mut transform) in &mut query {
+        let translation = position.previous.lerp(position.current, fraction);
+        if transform.translation != translation {
+            transform.translation = translation;
+        }
+    }
+}
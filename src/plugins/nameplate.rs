@@ -0,0 +1,207 @@
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+use crate::player::{self, EntityType, Experience, Health, PlayerType};
+use crate::views::ViewState;
+
+const HEALTH_COLOR: Color = Color::srgb(0.8, 0.1, 0.1);
+const HEALTH_BACKGROUND: Color = Color::srgb(0.2, 0.05, 0.05);
+
+// where nameplates sit and how big they are at normal zoom, and the
+// distances from the player over which other characters' plates fade out
+#[derive(Resource, Debug, Clone)]
+pub struct NameplateSettings {
+    pub height: f32,
+    pub font_size: f32,
+    pub bar_size: Vec2,
+    pub fade_start: f32,
+    pub fade_end: f32,
+}
+
+impl Default for NameplateSettings {
+    fn default() -> Self {
+        Self {
+            height: 260.,
+            font_size: 50.,
+            bar_size: Vec2::new(200., 16.),
+            fade_start: 1500.,
+            fade_end: 3000.,
+        }
+    }
+}
+
+// the name, level and health bar drawn above a character
+#[derive(Component)]
+pub struct Nameplate;
+
+#[derive(Component)]
+struct NameplateText;
+
+#[derive(Component)]
+struct NameplateBar;
+
+#[derive(Component)]
+struct NameplateBackground;
+
+pub struct NameplatePlugin;
+
+impl Plugin for NameplatePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<NameplateSettings>()
+            .add_systems(Update, (
+                add_nameplates,
+                (update_text, update_health, scale_nameplates, fade_nameplates)
+            )
+                .chain()
+                .run_if(in_state(ViewState::Game)));
+    }
+}
+
+fn label(name: &player::Name, experience: &Experience) -> String {
+    format!("{}  Lv {}", name.as_str(), experience.level)
+}
+
+fn add_nameplates(
+    mut commands: Commands,
+    settings: Res<NameplateSettings>,
+    query: Query<(Entity, &player::Name, &Experience, &Health), Added<EntityType>>,
+) {
+    for (entity, name, experience, health) in &query {
+        let bar = settings.bar_size;
+
+        let plate = (
+            Transform::from_translation(Vec3::new(0.0, settings.height, 1.0)),
+            Visibility::Inherited,
+            Nameplate,
+        );
+
+        commands.entity(entity).with_children(|parent| {
+            parent
+                .spawn(plate)
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text2d::new(label(name, experience)),
+                        TextFont {
+                            font_size: settings.font_size,
+                            ..default()
+                        },
+                        TextLayout::new_with_justify(JustifyText::Center),
+                        NameplateText,
+                    ));
+
+                    parent.spawn((
+                        Sprite {
+                            color: HEALTH_BACKGROUND,
+                            custom_size: Some(bar),
+                            ..default()
+                        },
+                        Transform::from_translation(Vec3::new(0.0, -settings.font_size * 0.8, 0.0)),
+                        NameplateBackground,
+                    ));
+
+                    // anchored on the left so it shrinks toward it
+                    parent.spawn((
+                        Sprite {
+                            color: HEALTH_COLOR,
+                            custom_size: Some(bar),
+                            anchor: Anchor::CenterLeft,
+                            ..default()
+                        },
+                        Transform::from_translation(Vec3::new(-bar.x / 2., -settings.font_size * 0.8, 0.1))
+                            .with_scale(Vec3::new(health.fraction(), 1., 1.)),
+                        NameplateBar,
+                    ));
+                });
+        });
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_text(
+    characters: Query<(&player::Name, &Experience, &Children), Or<(Changed<player::Name>, Changed<Experience>)>>,
+    plates: Query<&Children, With<Nameplate>>,
+    mut texts: Query<&mut Text2d, With<NameplateText>>,
+) {
+    for (name, experience, children) in &characters {
+        for plate in children.iter().filter_map(|c| plates.get(*c).ok()) {
+            for child in plate.iter() {
+                if let Ok(mut text) = texts.get_mut(*child) {
+                    text.0 = label(name, experience);
+                }
+            }
+        }
+    }
+}
+
+fn update_health(
+    characters: Query<(&Health, &Children), Changed<Health>>,
+    plates: Query<&Children, With<Nameplate>>,
+    mut bars: Query<&mut Transform, With<NameplateBar>>,
+) {
+    for (health, children) in &characters {
+        for plate in children.iter().filter_map(|c| plates.get(*c).ok()) {
+            for child in plate.iter() {
+                if let Ok(mut transform) = bars.get_mut(*child) {
+                    transform.scale.x = health.fraction();
+                }
+            }
+        }
+    }
+}
+
+// zooming out makes the world smaller on screen, so nameplates are grown
+// by the same amount to stay the same size
+fn scale_nameplates(
+    camera: Query<Ref<OrthographicProjection>, With<Camera2d>>,
+    mut plates: Query<(&mut Transform, Ref<Nameplate>)>,
+) {
+    let Ok(projection) = camera.get_single() else {
+        return;
+    };
+
+    for (mut transform, plate) in &mut plates {
+        if projection.is_changed() || plate.is_added() {
+            transform.scale = Vec3::splat(projection.scale);
+        }
+    }
+}
+
+// other characters' nameplates fade out as they get further away
+#[allow(clippy::type_complexity)]
+fn fade_nameplates(
+    settings: Res<NameplateSettings>,
+    player: Query<Ref<GlobalTransform>, With<PlayerType>>,
+    characters: Query<(Ref<GlobalTransform>, &Children), (With<EntityType>, Without<PlayerType>)>,
+    plates: Query<(Ref<Nameplate>, &Children)>,
+    mut texts: Query<&mut TextColor, With<NameplateText>>,
+    mut sprites: Query<(&mut Sprite, Has<NameplateBar>), Or<(With<NameplateBar>, With<NameplateBackground>)>>,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+
+    let span = (settings.fade_end - settings.fade_start).max(1.);
+
+    for (transform, children) in &characters {
+        let moved = player.is_changed() || transform.is_changed() || settings.is_changed();
+        let distance = player.translation().truncate().distance(transform.translation().truncate());
+        let alpha = 1. - ((distance - settings.fade_start) / span).clamp(0., 1.);
+
+        for (plate, parts) in children.iter().filter_map(|c| plates.get(*c).ok()) {
+            if !moved && !plate.is_added() {
+                continue;
+            }
+
+            for child in parts.iter() {
+                if let Ok(mut color) = texts.get_mut(*child) {
+                    color.0.set_alpha(alpha);
+                }
+                if let Ok((mut sprite, is_bar)) = sprites.get_mut(*child) {
+                    let base = if is_bar { HEALTH_COLOR } else { HEALTH_BACKGROUND };
+                    sprite.color = base.with_alpha(alpha);
+                }
+            }
+        }
+    }
+}
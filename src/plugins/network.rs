@@ -0,0 +1,516 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+use async_channel::{Receiver, Sender, TrySendError};
+use async_std::task::sleep;
+use async_tungstenite::async_std::{connect_async, ConnectStream};
+use async_tungstenite::WebSocketStream;
+use bevy::prelude::*;
+use bevy::time::common_conditions::on_timer;
+use futures_util::future::{select, Either};
+use futures_util::pin_mut;
+use futures_util::stream::StreamExt;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tinker_records::messages::{DisconnectMessage, Header, Message, Value};
+use tungstenite as ts;
+use tungstenite::protocol::frame::coding::CloseCode;
+
+use crate::chat::ChatMessage;
+use crate::config::ClientConfig;
+use crate::plugins::shutdown::{CancellationToken, ShutdownCoordinator};
+use crate::state::ConnectionState;
+use crate::stats::StatMessage;
+use crate::views::ViewState;
+
+// maximum number of messages buffered in each direction, and waiting
+// for room in the outgoing channel
+const CHANNEL_CAPACITY: usize = 256;
+
+// reconnection attempts before the connection is considered failed
+const MAX_ATTEMPTS: u32 = 10;
+
+// bounds for the delay between reconnection attempts
+pub const BACKOFF_BASE: Duration = Duration::from_millis(500);
+pub const BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+// added to the connect url when reconnecting. The server is expected to
+// resume the session for the token and resend `Value::Initial`, so
+// characters that came and went while disconnected are caught up.
+const RESYNC_QUERY: &str = "?resync=true";
+
+// how often the counters are written to the debug log while connected
+const COUNTER_INTERVAL: Duration = Duration::from_secs(10);
+
+// a message received from the server
+#[derive(Event, Debug)]
+pub struct Incoming(pub Message);
+
+// a message to be sent to the server
+#[derive(Event, Debug)]
+pub struct Outgoing(pub Message);
+
+impl Outgoing {
+    // tells the server the player is leaving, so others see them go
+    // straight away rather than when the socket times out
+    pub fn disconnect(account_id: i32) -> Self {
+        Self(Message {
+            header: Header { account_id },
+            value: Value::Disconnect(DisconnectMessage {}),
+        })
+    }
+}
+
+// a line of chat received from the server
+#[derive(Event, Debug)]
+pub struct IncomingChat(pub ChatMessage);
+
+// a line of chat to be sent to the server
+#[derive(Event, Debug)]
+pub struct OutgoingChat(pub ChatMessage);
+
+// a change to a character's stats received from the server
+#[derive(Event, Debug)]
+pub struct IncomingStats(pub StatMessage);
+
+// anything sent over the websocket. Record messages are written exactly
+// as before, and chat and stats are wrapped so they can't be confused.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum Packet {
+    Record(Message),
+    Chat { chat: ChatMessage },
+    Stats { stats: StatMessage },
+}
+
+// the lifecycle of the websocket connection
+#[derive(Resource, Clone, Copy, Default, PartialEq, Debug)]
+pub enum ConnectionStatus {
+    #[default]
+    Connecting,
+    Connected,
+    Reconnecting(u32),
+    Failed,
+    // the server ended the session on purpose, so it isn't retried
+    Closed,
+}
+
+// running totals for tuning how often movement is sent
+#[derive(Resource, Clone, Copy, Default, Debug)]
+pub struct NetworkCounters {
+    // messages handed to the connection
+    pub sent: u64,
+    // frames where movement would once have been sent but wasn't
+    pub suppressed: u64,
+    // moves repeated without a change, see `SendSettings::keyframe`
+    pub keyframes: u64,
+    // queued moves replaced by a newer one before they were sent
+    pub coalesced: u64,
+    // messages held back a frame because the send queue was full
+    pub deferred: u64,
+}
+
+// whether messages are backing up because the connection can't keep up.
+// Anything that doesn't fit is held in `Outgoing` and retried.
+#[derive(Resource, Clone, Copy, Default, PartialEq, Debug)]
+pub struct Backpressure(pub bool);
+
+// the channels and task for the current websocket connection
+#[derive(Resource)]
+pub struct NetworkConnection {
+    incoming: Receiver<Packet>,
+    outgoing: Sender<Packet>,
+    status: Receiver<ConnectionStatus>,
+    // waiting for room in `outgoing`, at most `CHANNEL_CAPACITY` long
+    pending: VecDeque<Packet>,
+    token: CancellationToken,
+}
+
+pub struct NetworkPlugin;
+
+impl Plugin for NetworkPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<Incoming>()
+            .add_event::<Outgoing>()
+            .add_event::<IncomingChat>()
+            .add_event::<OutgoingChat>()
+            .add_event::<IncomingStats>()
+            .init_resource::<ConnectionStatus>()
+            .init_resource::<NetworkCounters>()
+            .init_resource::<Backpressure>()
+
+            .add_systems(OnEnter(ViewState::Game), connect)
+            .add_systems(OnExit(ViewState::Game), disconnect)
+
+            .add_systems(PreUpdate, (receive_status, receive_messages)
+                .run_if(resource_exists::<NetworkConnection>))
+            .add_systems(PostUpdate, send_messages)
+            .add_systems(Last, log_counters
+                .run_if(resource_exists::<NetworkConnection>)
+                .run_if(on_timer(COUNTER_INTERVAL)));
+    }
+}
+
+impl NetworkConnection {
+    fn close(self) {
+        // the task closes the socket itself once cancelled, and is
+        // waited on by the coordinator if the app exits meanwhile
+        self.token.cancel();
+        self.outgoing.close();
+        self.incoming.close();
+        self.status.close();
+    }
+}
+
+fn connect(
+    mut commands: Commands,
+    mut coordinator: ResMut<ShutdownCoordinator>,
+    mut status: ResMut<ConnectionStatus>,
+    state: Res<ConnectionState>,
+    config: Res<ClientConfig>,
+) {
+    let Some(token) = state.token.clone() else {
+        warn!("Cannot connect without a session token");
+        return;
+    };
+
+    let (incoming_sender, incoming) = async_channel::bounded(CHANNEL_CAPACITY);
+    let (outgoing, outgoing_receiver) = async_channel::bounded(CHANNEL_CAPACITY);
+    let (status_sender, status_receiver) = async_channel::unbounded();
+
+    let url = config.ws_url(&format!("/connect/{}",token));
+    let token = coordinator.spawn("connection", |token| connection_task(
+        url,
+        incoming_sender,
+        outgoing_receiver,
+        status_sender,
+        token
+    ));
+
+    *status = ConnectionStatus::Connecting;
+    commands.insert_resource(NetworkConnection {
+        incoming,
+        outgoing,
+        status: status_receiver,
+        pending: VecDeque::with_capacity(CHANNEL_CAPACITY),
+        token,
+    });
+}
+
+fn disconnect(world: &mut World) {
+    if let Some(mut connection) = world.remove_resource::<NetworkConnection>() {
+        // messages sent while leaving, like a disconnect, haven't been
+        // queued yet. Whatever fits is still sent before the socket closes.
+        if let Some(mut events) = world.get_resource_mut::<Events<Outgoing>>() {
+            connection.pending.extend(events.drain().map(|Outgoing(m)| Packet::Record(m)));
+        }
+        while let Some(message) = connection.pending.pop_front() {
+            if connection.outgoing.try_send(message).is_err() {
+                break;
+            }
+        }
+        connection.close();
+    }
+}
+
+fn log_counters(counters: Res<NetworkCounters>) {
+    debug!(
+        "Network: {} sent, {} suppressed, {} keyframes, {} coalesced, {} deferred",
+        counters.sent,
+        counters.suppressed,
+        counters.keyframes,
+        counters.coalesced,
+        counters.deferred
+    );
+}
+
+fn receive_status(
+    connection: Res<NetworkConnection>,
+    mut status: ResMut<ConnectionStatus>,
+) {
+    while let Ok(value) = connection.status.try_recv() {
+        *status = value;
+    }
+}
+
+fn receive_messages(
+    connection: Res<NetworkConnection>,
+    mut events: EventWriter<Incoming>,
+    mut chat: EventWriter<IncomingChat>,
+    mut stats: EventWriter<IncomingStats>,
+) {
+    while let Ok(packet) = connection.incoming.try_recv() {
+        match packet {
+            Packet::Record(message) => {
+                events.send(Incoming(message));
+            },
+            Packet::Chat { chat: message } => {
+                chat.send(IncomingChat(message));
+            },
+            Packet::Stats { stats: message } => {
+                stats.send(IncomingStats(message));
+            },
+        }
+    }
+}
+
+fn send_messages(
+    mut events: ResMut<Events<Outgoing>>,
+    mut chat: ResMut<Events<OutgoingChat>>,
+    mut counters: ResMut<NetworkCounters>,
+    mut backpressure: ResMut<Backpressure>,
+    connection: Option<ResMut<NetworkConnection>>,
+) {
+    let Some(mut connection) = connection else {
+        events.clear();
+        chat.clear();
+        backpressure.set_if_neq(Backpressure(false));
+        return;
+    };
+
+    let mut held = Vec::new();
+    let mut held_chat = Vec::new();
+
+    for Outgoing(message) in events.drain() {
+        // only the newest position matters, so a move still waiting
+        // to be sent is replaced rather than queued behind
+        if let Value::Move(_) = &message.value {
+            let queued = connection.pending
+                .iter_mut()
+                .rev()
+                .filter_map(|p| match p {
+                    Packet::Record(m) => Some(m),
+                    _ => None,
+                })
+                .find(|m| matches!(m.value, Value::Move(_)) && m.header.account_id == message.header.account_id);
+
+            if let Some(queued) = queued {
+                *queued = message;
+                counters.coalesced += 1;
+                continue;
+            }
+        }
+
+        if connection.pending.len() < CHANNEL_CAPACITY {
+            connection.pending.push_back(Packet::Record(message));
+        } else {
+            held.push(message);
+        }
+    }
+
+    for OutgoingChat(message) in chat.drain() {
+        if connection.pending.len() < CHANNEL_CAPACITY {
+            connection.pending.push_back(Packet::Chat { chat: message });
+        } else {
+            held_chat.push(message);
+        }
+    }
+
+    // anything the channel can't take right now is retried next frame
+    while let Some(message) = connection.pending.pop_front() {
+        match connection.outgoing.try_send(message) {
+            Ok(()) => counters.sent += 1,
+            Err(TrySendError::Full(message)) => {
+                connection.pending.push_front(message);
+                break;
+            },
+            Err(TrySendError::Closed(_)) => {
+                connection.pending.clear();
+                break;
+            }
+        }
+    }
+
+    // events are kept for two frames, so sending these again means they
+    // are read next frame in the same order rather than lost
+    counters.deferred += (held.len() + held_chat.len()) as u64;
+    backpressure.set_if_neq(Backpressure(!held.is_empty() || !held_chat.is_empty()));
+
+    for message in held {
+        events.send(Outgoing(message));
+    }
+    for message in held_chat {
+        chat.send(OutgoingChat(message));
+    }
+}
+
+enum Activity {
+    Received(Packet),
+    Sending(Packet),
+    Ignored,
+    Closed,
+    Ended,
+    Lost,
+}
+
+// why a connection stopped running
+enum Exit {
+    // the client asked for the connection to end
+    Closed,
+    // the server closed the connection and doesn't want it back
+    Ended,
+    // the connection dropped and should be re-established
+    Lost,
+}
+
+// random delay in [delay/2, delay] where delay doubles with each attempt
+pub fn backoff(attempt: u32) -> Duration {
+    let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+    let delay = BACKOFF_BASE.saturating_mul(factor).min(BACKOFF_MAX);
+    let half = delay / 2;
+    half + half.mul_f64(rand::thread_rng().gen::<f64>())
+}
+
+// close codes sent when the server means to end the session, e.g. after
+// kicking the player. Anything else, including a server going away to
+// restart, is reconnected.
+fn deliberate(code: CloseCode) -> bool {
+    matches!(code, CloseCode::Normal | CloseCode::Policy)
+}
+
+// sleep for `delay`, returning false if the connection was cancelled meanwhile
+async fn wait(delay: Duration, token: &CancellationToken) -> bool {
+    let timer = sleep(delay);
+    let cancelled = token.cancelled();
+
+    pin_mut!(timer, cancelled);
+
+    matches!(select(timer, cancelled).await, Either::Left(_))
+}
+
+async fn connection_task(
+    url: String,
+    incoming: Sender<Packet>,
+    outgoing: Receiver<Packet>,
+    status: Sender<ConnectionStatus>,
+    token: CancellationToken,
+) {
+    let mut attempt = 0;
+    let mut connected_once = false;
+
+    loop {
+        if token.is_cancelled() {
+            return;
+        }
+
+        // once a connection has succeeded the token is reused to resume
+        // the session, and the server is asked to resend the world state
+        let target = if connected_once {
+            format!("{}{}",url,RESYNC_QUERY)
+        } else {
+            url.clone()
+        };
+
+        let connecting = connect_async(&target);
+        let cancelled = token.cancelled();
+
+        pin_mut!(connecting, cancelled);
+
+        let result = match select(connecting, cancelled).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => return,
+        };
+
+        match result {
+            Ok((stream, _)) => {
+                attempt = 0;
+                connected_once = true;
+                status.try_send(ConnectionStatus::Connected).ok();
+
+                match run_connection(stream, &incoming, &outgoing, &token).await {
+                    Exit::Closed => return,
+                    Exit::Ended => {
+                        status.try_send(ConnectionStatus::Closed).ok();
+                        return;
+                    },
+                    Exit::Lost => (),
+                }
+            },
+            Err(e) => {
+                warn!("Failed to connect: {}", e);
+            }
+        }
+
+        attempt += 1;
+
+        if attempt > MAX_ATTEMPTS {
+            error!("Giving up after {} reconnection attempts", MAX_ATTEMPTS);
+            status.try_send(ConnectionStatus::Failed).ok();
+            return;
+        }
+
+        status.try_send(ConnectionStatus::Reconnecting(attempt)).ok();
+
+        if !wait(backoff(attempt), &token).await {
+            return;
+        }
+    }
+}
+
+async fn run_connection(
+    mut stream: WebSocketStream<ConnectStream>,
+    incoming: &Sender<Packet>,
+    outgoing: &Receiver<Packet>,
+    token: &CancellationToken,
+) -> Exit {
+    let exit = loop {
+        let source = stream.next();
+        let sink = outgoing.recv();
+        let cancelled = token.cancelled();
+
+        pin_mut!(sink, cancelled);
+
+        let activity = match select(source, select(sink, cancelled)).await {
+            Either::Left((Some(Ok(ts::Message::Text(value))), _)) => {
+                match serde_json::from_slice(value.as_bytes()) {
+                    Ok(message) => Activity::Received(message),
+                    Err(e) => {
+                        warn!("Could not parse message: {}", e);
+                        Activity::Ignored
+                    }
+                }
+            },
+            Either::Left((Some(Ok(ts::Message::Close(frame))), _)) => {
+                if frame.as_ref().is_some_and(|f| deliberate(f.code)) {
+                    Activity::Ended
+                } else {
+                    Activity::Lost
+                }
+            },
+            Either::Left((Some(Ok(_)), _)) => Activity::Ignored,
+            Either::Left((Some(Err(_)) | None, _)) => Activity::Lost,
+            Either::Right((Either::Left((Ok(message), _)), _)) => Activity::Sending(message),
+            Either::Right((Either::Left((Err(_), _)), _)) => Activity::Closed,
+            Either::Right((Either::Right(_), _)) => Activity::Closed,
+        };
+
+        match activity {
+            Activity::Received(message) => {
+                // waits for room in the channel instead of dropping
+                if incoming.send(message).await.is_err() {
+                    break Exit::Closed;
+                }
+            },
+            Activity::Sending(message) => {
+                match serde_json::to_string(&message) {
+                    Ok(message) => {
+                        if stream.send(ts::Message::text(message)).await.is_err() {
+                            break Exit::Lost;
+                        }
+                    },
+                    Err(e) => warn!("Could not serialize message: {}", e)
+                }
+            },
+            Activity::Ignored => (),
+            Activity::Closed => break Exit::Closed,
+            Activity::Ended => break Exit::Ended,
+            Activity::Lost => break Exit::Lost,
+        }
+    };
+
+    if let Exit::Closed = exit {
+        stream.close(None).await.ok();
+    }
+
+    exit
+}
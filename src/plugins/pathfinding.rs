@@ -0,0 +1,532 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use bevy::prelude::*;
+use bevy_ecs_tiled::prelude::*;
+
+use crate::player::{Position, Target};
+use crate::plugins::interpolation::SnapshotBuffer;
+use crate::views::ViewState;
+
+// moves to the eight neighbouring tiles, straight ones first
+const NEIGHBOURS: [IVec2; 8] = [
+    IVec2::new(1, 0),
+    IVec2::new(-1, 0),
+    IVec2::new(0, 1),
+    IVec2::new(0, -1),
+    IVec2::new(1, 1),
+    IVec2::new(1, -1),
+    IVec2::new(-1, 1),
+    IVec2::new(-1, -1),
+];
+
+// object layers with this name (in any case) hold the map's collision shapes
+pub const COLLISION_LAYER: &str = "collision";
+
+// which tiles of an isometric (diamond) map can be walked on, and how
+// costly each is to cross. Tiles are numbered the way Tiled numbers them,
+// with `y` growing down the map, and the centre of the map is at the
+// world origin.
+#[derive(Resource, Debug, Clone)]
+pub struct Walkability {
+    width: i32,
+    height: i32,
+    tile_size: Vec2,
+    blocked: Vec<bool>,
+    costs: Vec<f32>,
+}
+
+// the tiles to walk through to reach a target, as world positions
+#[derive(Component, Debug, Default)]
+pub struct Path {
+    waypoints: VecDeque<Vec3>,
+}
+
+// systems that choose where characters walk to. Routes are planned
+// after them, so a new target never starts out on the old route.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChooseTarget;
+
+pub struct PathfindingPlugin;
+
+impl Plugin for PathfindingPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnExit(ViewState::Game), remove_walkability)
+            .add_systems(Update, load_walkability
+                .run_if(in_state(ViewState::Game))
+                .run_if(not(resource_exists::<Walkability>)))
+            .add_systems(Update, plan_paths
+                .after(load_walkability)
+                .after(ChooseTarget)
+                .run_if(in_state(ViewState::Game)));
+    }
+}
+
+impl Walkability {
+
+    // a map where every tile can be walked on
+    pub fn new(width: u32, height: u32, tile_size: Vec2) -> Self {
+        let count = (width * height) as usize;
+        Self {
+            width: width as i32,
+            height: height as i32,
+            tile_size,
+            blocked: vec![false; count],
+            costs: vec![1.; count],
+        }
+    }
+
+    // tiles that no layer draws anything on are holes in the map. Tiles
+    // may set `walkable` and `cost` properties in their tileset, and any
+    // shape on a collision layer blocks the tiles under it.
+    pub fn from_map(map: &tiled::Map) -> Self {
+        let mut grid = Self::new(
+            map.width,
+            map.height,
+            Vec2::new(map.tile_width as f32, map.tile_height as f32)
+        );
+
+        let mut drawn = vec![false; grid.blocked.len()];
+
+        for layer in map.layers() {
+            let collision = layer.name.eq_ignore_ascii_case(COLLISION_LAYER)
+                || property_bool(&layer.properties, "collision") == Some(true);
+
+            if !collision {
+                continue;
+            }
+
+            if let Some(objects) = layer.as_object_layer() {
+                for object in objects.objects() {
+                    grid.block_shape(&object, map.tile_height as f32);
+                }
+            }
+        }
+
+        for layer in map.layers().filter_map(|layer| layer.as_tile_layer()) {
+            for y in 0..grid.height {
+                for x in 0..grid.width {
+                    let Some(tile) = layer.get_tile(x, y) else {
+                        continue;
+                    };
+
+                    let position = IVec2::new(x, y);
+                    let index = (y * grid.width + x) as usize;
+                    drawn[index] = true;
+
+                    let Some(data) = tile.get_tile() else {
+                        continue;
+                    };
+
+                    if property_bool(&data.properties, "walkable") == Some(false) {
+                        grid.set_blocked(position, true);
+                    }
+
+                    // the most expensive layer wins
+                    if let Some(cost) = property_number(&data.properties, "cost") {
+                        let current = grid.costs[index];
+                        grid.set_cost(position, current.max(cost));
+                    }
+                }
+            }
+        }
+
+        for (index, drawn) in drawn.into_iter().enumerate() {
+            if !drawn {
+                grid.blocked[index] = true;
+            }
+        }
+
+        grid
+    }
+
+    pub fn size(&self) -> UVec2 {
+        UVec2::new(self.width as u32, self.height as u32)
+    }
+
+    pub fn contains(&self, tile: IVec2) -> bool {
+        tile.x >= 0 && tile.y >= 0 && tile.x < self.width && tile.y < self.height
+    }
+
+    pub fn is_walkable(&self, tile: IVec2) -> bool {
+        self.index(tile).is_some_and(|i| !self.blocked[i])
+    }
+
+    pub fn set_blocked(&mut self, tile: IVec2, blocked: bool) {
+        if let Some(i) = self.index(tile) {
+            self.blocked[i] = blocked;
+        }
+    }
+
+    // how much crossing a tile costs compared to open ground, or `None`
+    // if it can't be crossed at all
+    pub fn cost(&self, tile: IVec2) -> Option<f32> {
+        self.index(tile)
+            .filter(|&i| !self.blocked[i])
+            .map(|i| self.costs[i])
+    }
+
+    // costs below one would make routes look cheaper than they are, so
+    // they are treated as open ground
+    pub fn set_cost(&mut self, tile: IVec2, cost: f32) {
+        if let Some(i) = self.index(tile) {
+            self.costs[i] = cost.max(1.);
+        }
+    }
+
+    // the tile under a world position, which may be off the map
+    pub fn world_to_tile(&self, position: Vec3) -> IVec2 {
+        let half = self.tile_size / 2.;
+        let a = position.x / half.x;
+        let b = -position.y / half.y;
+
+        let u = (a + b) / 2. + self.width as f32 / 2.;
+        let v = (b - a) / 2. + self.height as f32 / 2.;
+
+        IVec2::new(u.floor() as i32, v.floor() as i32)
+    }
+
+    // the world position of the centre of a tile
+    pub fn tile_to_world(&self, tile: IVec2) -> Vec2 {
+        let half = self.tile_size / 2.;
+        let u = tile.x as f32 + 0.5 - self.width as f32 / 2.;
+        let v = tile.y as f32 + 0.5 - self.height as f32 / 2.;
+
+        Vec2::new((u - v) * half.x, -(u + v) * half.y)
+    }
+
+    // the cheapest walkable route between two tiles, including both ends,
+    // or `None` if there isn't one. Diagonal moves may not cut the corner
+    // of a blocked tile.
+    pub fn find_path(&self, start: IVec2, goal: IVec2) -> Option<Vec<IVec2>> {
+        if !self.is_walkable(goal) {
+            return None;
+        }
+
+        let (path, reached) = self.search(start, goal)?;
+        reached.then_some(path)
+    }
+
+    // like `find_path`, but if the goal can't be reached the route ends
+    // on the reachable tile closest to it instead
+    pub fn find_nearest_path(&self, start: IVec2, goal: IVec2) -> Option<Vec<IVec2>> {
+        self.search(start, goal).map(|(path, _)| path)
+    }
+
+    // a character standing on a blocked tile may still walk off it
+    fn search(&self, start: IVec2, goal: IVec2) -> Option<(Vec<IVec2>, bool)> {
+        if !self.contains(start) {
+            return None;
+        }
+
+        let mut open = BinaryHeap::new();
+        let mut came_from: HashMap<IVec2, IVec2> = HashMap::new();
+        let mut costs: HashMap<IVec2, f32> = HashMap::new();
+        let mut closest = (heuristic(start, goal), start);
+
+        costs.insert(start, 0.);
+        open.push(Node { tile: start, estimate: heuristic(start, goal) });
+
+        while let Some(Node { tile, .. }) = open.pop() {
+            if tile == goal {
+                return Some((Self::route(&came_from, goal), true));
+            }
+
+            let remaining = heuristic(tile, goal);
+            if remaining < closest.0 {
+                closest = (remaining, tile);
+            }
+
+            let cost = costs[&tile];
+
+            for offset in NEIGHBOURS {
+                let next = tile + offset;
+
+                let Some(tile_cost) = self.cost(next) else {
+                    continue;
+                };
+
+                let diagonal = offset.x != 0 && offset.y != 0;
+
+                if diagonal && (
+                    !self.is_walkable(tile + IVec2::new(offset.x, 0)) ||
+                    !self.is_walkable(tile + IVec2::new(0, offset.y))
+                ) {
+                    continue;
+                }
+
+                let step = if diagonal { std::f32::consts::SQRT_2 } else { 1. };
+                let next_cost = cost + step * tile_cost;
+
+                if costs.get(&next).is_none_or(|&c| next_cost < c) {
+                    costs.insert(next, next_cost);
+                    came_from.insert(next, tile);
+                    open.push(Node {
+                        tile: next,
+                        estimate: next_cost + heuristic(next, goal)
+                    });
+                }
+            }
+        }
+
+        Some((Self::route(&came_from, closest.1), false))
+    }
+
+    fn route(came_from: &HashMap<IVec2, IVec2>, end: IVec2) -> Vec<IVec2> {
+        let mut path = vec![end];
+        let mut current = end;
+        while let Some(&previous) = came_from.get(&current) {
+            path.push(previous);
+            current = previous;
+        }
+        path.reverse();
+        path
+    }
+
+    // Tiled stores object positions on isometric maps before projecting
+    // them, with each tile `unit` across in both directions
+    fn block_shape(&mut self, object: &tiled::ObjectData, unit: f32) {
+        let origin = Vec2::new(object.x, object.y) / unit;
+
+        let inside: Box<dyn Fn(Vec2) -> bool> = match &object.shape {
+            tiled::ObjectShape::Rect { width, height } => {
+                let end = origin + Vec2::new(*width, *height) / unit;
+                Box::new(move |p| p.cmpge(origin).all() && p.cmple(end).all())
+            },
+            tiled::ObjectShape::Ellipse { width, height } => {
+                let radius = Vec2::new(*width, *height) / unit / 2.;
+                let centre = origin + radius;
+                Box::new(move |p| ((p - centre) / radius).length_squared() <= 1.)
+            },
+            tiled::ObjectShape::Polygon { points } => {
+                let points: Vec<Vec2> = points
+                    .iter()
+                    .map(|&(x, y)| origin + Vec2::new(x, y) / unit)
+                    .collect();
+                Box::new(move |p| polygon_contains(&points, p))
+            },
+            tiled::ObjectShape::Polyline { points } => {
+                let points: Vec<Vec2> = points
+                    .iter()
+                    .map(|&(x, y)| origin + Vec2::new(x, y) / unit)
+                    .collect();
+
+                // walls drawn as lines block every tile they pass through
+                for pair in points.windows(2) {
+                    let samples = (pair[0].distance(pair[1]) * 4.).ceil().max(1.) as i32;
+                    for i in 0..=samples {
+                        let p = pair[0].lerp(pair[1], i as f32 / samples as f32);
+                        self.set_blocked(p.floor().as_ivec2(), true);
+                    }
+                }
+                return;
+            },
+            tiled::ObjectShape::Point(..) => {
+                self.set_blocked(origin.floor().as_ivec2(), true);
+                return;
+            },
+            _ => return,
+        };
+
+        // a tile is blocked when the shape covers its centre
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let centre = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                if inside(centre) {
+                    self.set_blocked(IVec2::new(x, y), true);
+                }
+            }
+        }
+    }
+
+    fn index(&self, tile: IVec2) -> Option<usize> {
+        self.contains(tile).then(|| (tile.y * self.width + tile.x) as usize)
+    }
+}
+
+impl Path {
+
+    pub fn new(waypoints: impl IntoIterator<Item = Vec3>) -> Self {
+        Self {
+            waypoints: waypoints.into_iter().collect(),
+        }
+    }
+
+    // the waypoint currently being walked toward
+    pub fn next(&self) -> Option<Vec3> {
+        self.waypoints.front().copied()
+    }
+
+    // move on to the following waypoint, returning false once there are none left
+    pub fn advance(&mut self) -> bool {
+        self.waypoints.pop_front();
+        !self.waypoints.is_empty()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.waypoints.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.waypoints.len()
+    }
+}
+
+// a tile waiting to be explored, ordered so the heap pops the cheapest
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    tile: IVec2,
+    estimate: f32,
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.estimate == other.estimate
+    }
+}
+
+impl Eq for Node {}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Node {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+fn property_bool(properties: &tiled::Properties, name: &str) -> Option<bool> {
+    match properties.get(name)? {
+        tiled::PropertyValue::BoolValue(value) => Some(*value),
+        _ => None,
+    }
+}
+
+fn property_number(properties: &tiled::Properties, name: &str) -> Option<f32> {
+    match properties.get(name)? {
+        tiled::PropertyValue::IntValue(value) => Some(*value as f32),
+        tiled::PropertyValue::FloatValue(value) => Some(*value),
+        _ => None,
+    }
+}
+
+// even-odd test for whether a point is inside a polygon
+fn polygon_contains(points: &[Vec2], p: Vec2) -> bool {
+    let mut inside = false;
+    let mut j = points.len().wrapping_sub(1);
+
+    for i in 0..points.len() {
+        let (a, b) = (points[i], points[j]);
+        if (a.y > p.y) != (b.y > p.y) && p.x < (b.x - a.x) * (p.y - a.y) / (b.y - a.y) + a.x {
+            inside = !inside;
+        }
+        j = i;
+    }
+
+    inside
+}
+
+// the cost of the best possible route with diagonal moves allowed
+fn heuristic(from: IVec2, to: IVec2) -> f32 {
+    let d = (to - from).abs();
+    let (low, high) = (d.x.min(d.y) as f32, d.x.max(d.y) as f32);
+    high + (std::f32::consts::SQRT_2 - 1.) * low
+}
+
+// drop tiles in the middle of straight runs, so characters only stop
+// turning where the route actually bends
+fn simplify(tiles: &[IVec2]) -> Vec<IVec2> {
+    let last = tiles.len().saturating_sub(1);
+
+    tiles
+        .iter()
+        .enumerate()
+        .filter(|&(i, &tile)| {
+            i == 0 || i == last || tile - tiles[i - 1] != tiles[i + 1] - tile
+        })
+        .map(|(_, &tile)| tile)
+        .collect()
+}
+
+// the map is loaded in the background, so the grid is built once it arrives
+fn load_walkability(
+    mut commands: Commands,
+    maps: Option<Res<Assets<TiledMap>>>,
+    query: Query<&TiledMapHandle>,
+) {
+    // headless clients never load a map
+    let Some(maps) = maps else {
+        return;
+    };
+
+    for handle in &query {
+        if let Some(map) = maps.get(&handle.0) {
+            commands.insert_resource(Walkability::from_map(&map.map));
+        }
+    }
+}
+
+fn remove_walkability(mut commands: Commands) {
+    commands.remove_resource::<Walkability>();
+}
+
+// find a route whenever a character is given somewhere new to go. Without
+// a map (in headless mode, or before it has loaded) they walk straight
+// there. Targets that can't be reached are moved to the closest tile
+// that can, and characters with nowhere to go stay put.
+#[allow(clippy::type_complexity)]
+fn plan_paths(
+    mut commands: Commands,
+    grid: Option<Res<Walkability>>,
+    mut query: Query<(Entity, &Position, &mut Target), (Changed<Target>, Without<SnapshotBuffer>)>,
+) {
+    for (entity, position, mut target) in &mut query {
+        let Some(point) = target.0 else {
+            commands.entity(entity).remove::<Path>();
+            continue;
+        };
+
+        let Some(grid) = grid.as_ref() else {
+            commands.entity(entity).insert(Path::new([point]));
+            continue;
+        };
+
+        let start = grid.world_to_tile(position.current);
+        let goal = grid.world_to_tile(point);
+
+        let tiles = grid
+            .find_nearest_path(start, goal)
+            .filter(|tiles| tiles.len() > 1 || goal == start);
+
+        // change detection is bypassed so the new target isn't planned again
+        let Some(tiles) = tiles else {
+            target.bypass_change_detection().0 = None;
+            commands.entity(entity).remove::<Path>();
+            continue;
+        };
+
+        let end = *tiles.last().unwrap_or(&start);
+        let point = if end == goal {
+            point
+        } else {
+            let clipped = grid.tile_to_world(end).extend(point.z);
+            target.bypass_change_detection().0 = Some(clipped);
+            clipped
+        };
+
+        // the first tile is the one the character is already standing on,
+        // and the last is replaced by the exact point that was chosen
+        let tiles = simplify(&tiles);
+        let last = tiles.len().saturating_sub(1);
+        let waypoints = tiles[1.min(last)..last]
+            .iter()
+            .map(|&tile| grid.tile_to_world(tile).extend(point.z))
+            .chain([point]);
+
+        commands.entity(entity).insert(Path::new(waypoints));
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::VecDeque;
+use bevy::prelude::*;
+use tinker_records::messages::Value;
+
+use crate::player::{PlayerType, Position};
+use crate::plugins::movement::{step, SimulateMovement};
+use crate::plugins::network::{ConnectionStatus, Incoming};
+use crate::state::ConnectionState;
+use crate::views::ViewState;
+
+// most unacknowledged inputs kept before the oldest are forgotten
+const HISTORY_CAPACITY: usize = 256;
+
+// errors smaller than this are left alone
+pub const CORRECTION_THRESHOLD: f32 = 2.;
+
+// errors larger than this are fixed immediately instead of blended
+const SNAP_DISTANCE: f32 = 300.;
+
+// fraction of the remaining error removed per second
+const CORRECTION_RATE: f32 = 10.;
+
+// one tick of local movement, with `speed` in units per second and
+// `position` where it left the player
+#[derive(Debug, Clone, Copy)]
+pub struct InputCommand {
+    pub sequence: u32,
+    pub position: Vec3,
+    pub target: Vec3,
+    pub speed: f32,
+    pub delta: f32,
+}
+
+// inputs the server may not have seen yet, and any error still being
+// corrected. The protocol has no sequence numbers and the server doesn't
+// echo the player's own moves, so the only answer is a `Move` for the
+// player that the server sends when it disagrees, e.g. after blocking or
+// teleporting them.
+#[derive(Resource, Debug, Default)]
+pub struct Prediction {
+    next: u32,
+    history: VecDeque<InputCommand>,
+    // the last input applied when a `Move` was last sent
+    sent: Option<u32>,
+    correction: Vec3,
+}
+
+pub struct PredictionPlugin;
+
+impl Plugin for PredictionPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Prediction>()
+            .add_systems(OnEnter(ViewState::Game), reset_prediction)
+            .add_systems(Update, reset_prediction
+                .run_if(resource_changed::<ConnectionStatus>)
+                .run_if(resource_equals(ConnectionStatus::Connected)))
+            .add_systems(Update, reconcile.run_if(in_state(ViewState::Game)))
+            .add_systems(FixedUpdate, apply_correction
+                .after(SimulateMovement)
+                .run_if(in_state(ViewState::Game)));
+    }
+}
+
+impl Prediction {
+
+    // remember an input that has just been applied and number it
+    pub fn record(&mut self, position: Vec3, target: Vec3, speed: f32, delta: f32) -> InputCommand {
+        let command = InputCommand {
+            sequence: self.next,
+            position,
+            target,
+            speed,
+            delta,
+        };
+
+        self.next = self.next.wrapping_add(1);
+
+        if self.history.len() >= HISTORY_CAPACITY {
+            self.history.pop_front();
+        }
+
+        self.history.push_back(command);
+        command
+    }
+
+    // note that a `Move` carrying the current position was sent
+    pub fn mark_sent(&mut self) {
+        self.sent = Some(self.next.wrapping_sub(1));
+    }
+
+    // accept a position from the server. If the player was predicted to
+    // pass through it since the last `Move` was sent the server is only
+    // behind, and the inputs up to there are settled. Older inputs aren't
+    // matched, as a path that doubles back could agree with a stale
+    // position. Otherwise it is taken as where the player was when the
+    // last `Move` was sent, and every input since is replayed to give
+    // where they should be now.
+    pub fn acknowledge(&mut self, authoritative: Vec3) -> Option<Vec3> {
+        let start = self.sent.and_then(|sequence| self.history
+            .iter()
+            .position(|c| c.sequence.wrapping_sub(sequence) as i32 >= 0));
+
+        let agreed = start.and_then(|start| self.history
+            .range(start..)
+            .position(|c| c.position.truncate().distance(authoritative.truncate()) <= CORRECTION_THRESHOLD)
+            .map(|index| start + index));
+
+        if let Some(index) = agreed {
+            self.history.drain(..=index);
+            return None;
+        }
+
+        if let Some(sequence) = self.sent {
+            // sequences wrap, so compare by distance rather than value
+            while self.history.front().is_some_and(|c| c.sequence.wrapping_sub(sequence) as i32 <= 0) {
+                self.history.pop_front();
+            }
+        }
+
+        let mut position = authoritative;
+
+        for command in self.history.iter() {
+            position = step(position, command.target, command.speed, command.delta);
+        }
+
+        Some(position)
+    }
+
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+// inputs in flight on an old connection will never be answered
+fn reset_prediction(mut prediction: ResMut<Prediction>) {
+    prediction.reset();
+}
+
+fn reconcile(
+    mut incoming: EventReader<Incoming>,
+    mut prediction: ResMut<Prediction>,
+    state: Res<ConnectionState>,
+    mut query: Query<&mut Position, With<PlayerType>>,
+) {
+    for Incoming(item) in incoming.read() {
+        let Value::Move(message) = &item.value else {
+            continue;
+        };
+
+        if item.header.account_id != state.id {
+            continue;
+        }
+
+        let Some(reconciled) = prediction.acknowledge(message.position) else {
+            continue;
+        };
+
+        for mut position in &mut query {
+            let error = reconciled - position.current;
+            let error = Vec3::new(error.x, error.y, 0.);
+
+            if error.length() > SNAP_DISTANCE {
+                warn!("Snapping to server position ({:.0} units off)", error.length());
+                let current = position.current + error;
+                position.teleport(current);
+                prediction.correction = Vec3::ZERO;
+            } else if error.length() > CORRECTION_THRESHOLD {
+                prediction.correction = error;
+            }
+        }
+    }
+}
+
+// blend any correction in over a few ticks instead of jumping
+fn apply_correction(
+    time: Res<Time>,
+    mut prediction: ResMut<Prediction>,
+    mut query: Query<&mut Position, With<PlayerType>>,
+) {
+    if prediction.correction == Vec3::ZERO {
+        return;
+    }
+
+    let amount = 1. - (-CORRECTION_RATE * time.delta_secs()).exp();
+    let mut applied = prediction.correction * amount;
+
+    if (prediction.correction - applied).length() < 0.5 {
+        applied = prediction.correction;
+    }
+
+    for mut position in &mut query {
+        position.current += applied;
+    }
+
+    prediction.correction -= applied;
+}
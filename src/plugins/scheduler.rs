@@ -0,0 +1,130 @@
+use std::time::Duration;
+use bevy::prelude::*;
+use tinker_records::messages::Message;
+
+use crate::player::{AccountId, PlayerType, Position, Speed, Target};
+use crate::plugins::network::{NetworkCounters, Outgoing};
+use crate::plugins::pathfinding::Path;
+use crate::plugins::prediction::Prediction;
+use crate::views::ViewState;
+
+// how often movement may be sent. Moves are only sent when the target
+// or speed changes, unless `keyframe` is set to also repeat them that
+// often while walking.
+#[derive(Resource, Debug, Clone)]
+pub struct SendSettings {
+    pub tick_rate: f32,
+    pub keyframe: Option<Duration>,
+}
+
+impl Default for SendSettings {
+    fn default() -> Self {
+        Self {
+            tick_rate: 20.,
+            keyframe: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SentMove {
+    target: Vec3,
+    speed: f32,
+}
+
+#[derive(Resource, Debug, Default)]
+struct SendScheduler {
+    timer: Timer,
+    last: Option<SentMove>,
+    since_last: Duration,
+}
+
+pub struct SchedulerPlugin;
+
+impl Plugin for SchedulerPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<SendSettings>()
+            .init_resource::<SendScheduler>()
+            .add_systems(OnEnter(ViewState::Game), reset_scheduler)
+            // movement is simulated in `FixedUpdate`, which runs first
+            .add_systems(Update, schedule_moves.run_if(in_state(ViewState::Game)));
+    }
+}
+
+fn reset_scheduler(
+    settings: Res<SendSettings>,
+    mut scheduler: ResMut<SendScheduler>,
+) {
+    *scheduler = SendScheduler {
+        timer: Timer::from_seconds(1. / settings.tick_rate.max(1.), TimerMode::Repeating),
+        last: None,
+        since_last: Duration::ZERO,
+    };
+}
+
+#[allow(clippy::type_complexity)]
+fn schedule_moves(
+    time: Res<Time>,
+    settings: Res<SendSettings>,
+    mut scheduler: ResMut<SendScheduler>,
+    mut counters: ResMut<NetworkCounters>,
+    mut prediction: ResMut<Prediction>,
+    query: Query<(&AccountId, &Position, &Speed, &Target, Option<&Path>), With<PlayerType>>,
+    mut outgoing: EventWriter<Outgoing>,
+) {
+    let Ok((id, position, speed, target, path)) = query.get_single() else {
+        return;
+    };
+
+    let position = position.current;
+    let moving = target.0.is_some();
+
+    // other clients are told about the next corner of the path rather
+    // than the final target, so they don't cut across blocked tiles
+    let heading = path.and_then(|p| p.next()).or(target.0);
+
+    // stopping is sent as a move to where the player already is
+    let current = match heading {
+        Some(point) => SentMove {
+            target: Vec3::new(point.x, point.y, position.z),
+            speed: speed.fixed.unwrap_or(0.),
+        },
+        None => SentMove {
+            target: position,
+            speed: 0.,
+        },
+    };
+
+    scheduler.since_last += time.delta();
+
+    let tick = scheduler.timer.tick(time.delta()).just_finished();
+    let changed = match scheduler.last {
+        Some(last) => (moving && last != current) || (!moving && last.speed != 0.),
+        None => true,
+    };
+    let keyframe = moving && settings.keyframe.is_some_and(|every| scheduler.since_last >= every);
+
+    if !tick || !(changed || keyframe) {
+        // the old behaviour sent every frame while moving
+        if moving {
+            counters.suppressed += 1;
+        }
+        return;
+    }
+
+    if !changed {
+        counters.keyframes += 1;
+    }
+
+    prediction.mark_sent();
+    outgoing.send(Outgoing(Message::Move(
+        id.0,
+        current.speed,
+        current.target,
+        position
+    )));
+
+    scheduler.last = Some(current);
+    scheduler.since_last = Duration::ZERO;
+}
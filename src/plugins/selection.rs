@@ -0,0 +1,421 @@
+use bevy::color::palettes::css::{FIRE_BRICK, GOLD};
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::player::{self, AccountId, CharacterType, Experience, Health, PlayerType, Speed};
+use crate::plugins::button::{MyButton, MyButtonLabel};
+use crate::plugins::follow::Follow;
+use crate::views::game::OnGame;
+use crate::views::ViewState;
+
+// size of the ring drawn under a selected character
+const RING_SIZE: Vec2 = Vec2::new(90., 45.);
+
+// how far below the middle of a character's sprite their feet are
+const FEET_OFFSET: f32 = 180.;
+
+// sprites without an atlas or custom size are picked with this size
+const DEFAULT_SPRITE_SIZE: Vec2 = Vec2::new(255., 512.);
+
+const MENU_BUTTON: Color = Color::srgb(1.0, 0.84, 0.0);
+const MENU_BUTTON_HOVERED: Color = Color::srgb(1.0, 0.92, 0.5);
+
+// the character the player has clicked on
+#[derive(Component, Debug, Default)]
+pub struct Selected;
+
+// clicks on characters are handled here before anything else sees them
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PickingSet;
+
+// something chosen from a character's context menu
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interact {
+    Follow(Entity),
+    Inspect(Entity),
+}
+
+#[derive(Component, Default, Clone, Copy)]
+enum ContextAction {
+    #[default]
+    Follow,
+    Inspect,
+}
+
+#[derive(Component)]
+struct ContextMenu(Entity);
+
+#[derive(Component)]
+struct TargetFrame;
+
+// each line of text in the target frame
+#[derive(Component, Clone, Copy, PartialEq)]
+enum TargetText {
+    Name,
+    Level,
+    Health,
+    Details,
+}
+
+#[derive(Component)]
+struct TargetHealthBar;
+
+// whether the target frame shows the extra details from "Inspect"
+#[derive(Resource, Default)]
+struct Inspecting(bool);
+
+pub struct SelectionPlugin;
+
+impl Plugin for SelectionPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<Interact>()
+            .init_resource::<Inspecting>()
+            .add_systems(OnEnter(ViewState::Game), setup_target_frame)
+            .add_systems(Update, (close_context_menu, pick_characters)
+                .chain()
+                .in_set(PickingSet)
+                .run_if(in_state(ViewState::Game)))
+            .add_systems(Update, (
+                context_menu_buttons,
+                context_menu_action,
+                inspect,
+                update_target_frame,
+                draw_selection
+            )
+                .chain()
+                .run_if(in_state(ViewState::Game)));
+    }
+}
+
+// the world position under the mouse, if it's over the window
+fn cursor_world_position(
+    windows: &Query<&Window, With<PrimaryWindow>>,
+    camera: &Query<(&Camera, &GlobalTransform)>,
+) -> Option<Vec2> {
+    let (camera, camera_transform) = camera.get_single().ok()?;
+    windows
+        .get_single()
+        .ok()?
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor).ok())
+}
+
+// the area a sprite covers on screen, centred on its position
+fn sprite_size(sprite: &Sprite, layouts: &Assets<TextureAtlasLayout>) -> Vec2 {
+    sprite.custom_size
+        .or_else(|| sprite.texture_atlas
+            .as_ref()
+            .and_then(|atlas| atlas.texture_rect(layouts))
+            .map(|rect| rect.size().as_vec2()))
+        .unwrap_or(DEFAULT_SPRITE_SIZE)
+}
+
+// select the character under the mouse. The click is used up, so the
+// player doesn't also walk to where the character is standing.
+#[allow(clippy::too_many_arguments)]
+fn pick_characters(
+    mut commands: Commands,
+    mut buttons: ResMut<ButtonInput<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    layouts: Res<Assets<TextureAtlasLayout>>,
+    characters: Query<(Entity, &GlobalTransform, &Sprite), With<CharacterType>>,
+    selected: Query<Entity, With<Selected>>,
+    menus: Query<(), With<ContextMenu>>,
+    mut inspecting: ResMut<Inspecting>,
+) {
+    let left = buttons.just_pressed(MouseButton::Left);
+    let right = buttons.just_pressed(MouseButton::Right);
+
+    if !left && !right {
+        return;
+    }
+
+    let Some(point) = cursor_world_position(&windows, &camera) else {
+        return;
+    };
+
+    // characters drawn in front are picked first
+    let picked = characters
+        .iter()
+        .filter(|(_, transform, sprite)| {
+            let centre = transform.translation().truncate();
+            let half = sprite_size(sprite, &layouts) * transform.scale().truncate() / 2.;
+            (point - centre).abs().cmple(half).all()
+        })
+        .max_by(|(_, a, _), (_, b, _)| {
+            let (a, b) = (a.translation(), b.translation());
+            a.z.total_cmp(&b.z).then(b.y.total_cmp(&a.y))
+        })
+        .map(|(entity, _, _)| entity);
+
+    let Some(entity) = picked else {
+        return;
+    };
+
+    for previous in &selected {
+        if previous != entity {
+            commands.entity(previous).remove::<Selected>();
+            inspecting.0 = false;
+        }
+    }
+
+    commands.entity(entity).insert(Selected);
+    buttons.reset(MouseButton::Left);
+
+    if right && menus.is_empty() {
+        if let Ok(window) = windows.get_single() {
+            if let Some(cursor) = window.cursor_position() {
+                spawn_context_menu(&mut commands, entity, cursor);
+            }
+        }
+    }
+}
+
+fn spawn_context_menu(commands: &mut Commands, entity: Entity, cursor: Vec2) {
+    let menu = (
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(cursor.x),
+            top: Val::Px(cursor.y),
+            width: Val::Px(160.0),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(5.0),
+            padding: UiRect::all(Val::Px(5.0)),
+            ..default()
+        },
+        BackgroundColor(FIRE_BRICK.into()),
+        GlobalZIndex(10),
+        ContextMenu(entity),
+        OnGame
+    );
+
+    commands
+        .spawn(menu)
+        .with_children(|parent| {
+            for (label, action) in [
+                ("Follow", ContextAction::Follow),
+                ("Inspect", ContextAction::Inspect),
+            ] {
+                let mut button = MyButton::new(action);
+                button.background_color = MENU_BUTTON.into();
+                button.node.padding = UiRect::all(Val::Px(5.0));
+
+                parent
+                    .spawn(button)
+                    .with_child(MyButtonLabel::new(label));
+            }
+        });
+}
+
+// any click that isn't on the menu closes it
+fn close_context_menu(
+    mut commands: Commands,
+    mut buttons: ResMut<ButtonInput<MouseButton>>,
+    menus: Query<Entity, With<ContextMenu>>,
+    items: Query<&Interaction, With<ContextAction>>,
+) {
+    if menus.is_empty() {
+        return;
+    }
+
+    let clicked = buttons.any_just_pressed([MouseButton::Left, MouseButton::Right]);
+    let on_menu = items.iter().any(|i| *i != Interaction::None);
+
+    if on_menu {
+        // the button takes this click
+        buttons.reset(MouseButton::Left);
+    } else if clicked {
+        for menu in &menus {
+            commands.entity(menu).despawn_recursive();
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn context_menu_buttons(
+    mut query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<ContextAction>),
+    >,
+) {
+    for (interaction, mut background_color) in &mut query {
+        *background_color = match *interaction {
+            Interaction::Pressed | Interaction::Hovered => MENU_BUTTON_HOVERED.into(),
+            Interaction::None => MENU_BUTTON.into()
+        }
+    }
+}
+
+fn context_menu_action(
+    mut commands: Commands,
+    query: Query<(&Interaction, &ContextAction), Changed<Interaction>>,
+    menus: Query<(Entity, &ContextMenu)>,
+    mut interact: EventWriter<Interact>,
+) {
+    for (interaction, action) in &query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        for (menu, ContextMenu(entity)) in &menus {
+            interact.send(match action {
+                ContextAction::Follow => Interact::Follow(*entity),
+                ContextAction::Inspect => Interact::Inspect(*entity),
+            });
+            commands.entity(menu).despawn_recursive();
+        }
+    }
+}
+
+// "Follow" keeps the player walking after the character, and "Inspect"
+// shows more about them
+fn inspect(
+    mut commands: Commands,
+    mut events: EventReader<Interact>,
+    mut inspecting: ResMut<Inspecting>,
+    player: Query<Entity, With<PlayerType>>,
+) {
+    for event in events.read() {
+        match *event {
+            Interact::Inspect(_) => {
+                inspecting.0 = true;
+            },
+            Interact::Follow(entity) => {
+                for player in &player {
+                    commands.entity(player).insert(Follow(entity));
+                }
+            },
+        }
+    }
+}
+
+fn setup_target_frame(mut commands: Commands, mut inspecting: ResMut<Inspecting>) {
+    inspecting.0 = false;
+
+    let frame = (
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            // to the right of the player's own frame in the HUD
+            left: Val::Vw(25.0),
+            width: Val::Px(220.0),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(4.0),
+            padding: UiRect::all(Val::Px(8.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
+        Visibility::Hidden,
+        TargetFrame,
+        OnGame
+    );
+
+    let font = TextFont {
+        font_size: 16.0,
+        ..default()
+    };
+
+    commands
+        .spawn(frame)
+        .with_children(|parent| {
+            parent.spawn((Text::new(""), font.clone(), TargetText::Name));
+            parent.spawn((Text::new(""), font.clone(), TargetText::Level));
+
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Percent(100.0),
+                        height: Val::Px(12.0),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.3, 0.05, 0.05)),
+                ))
+                .with_child((
+                    Node {
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.8, 0.1, 0.1)),
+                    TargetHealthBar,
+                ));
+
+            parent.spawn((Text::new(""), font.clone(), TargetText::Health));
+            parent.spawn((Text::new(""), font, TargetText::Details));
+        });
+}
+
+#[allow(clippy::type_complexity)]
+fn update_target_frame(
+    inspecting: Res<Inspecting>,
+    selected: Query<(
+        Ref<Selected>,
+        &player::Name,
+        Ref<Health>,
+        Ref<Experience>,
+        &AccountId,
+        Ref<Speed>
+    )>,
+    mut frame: Query<&mut Visibility, With<TargetFrame>>,
+    mut texts: Query<(&TargetText, &mut Text, &mut Node), Without<TargetHealthBar>>,
+    mut bar: Query<&mut Node, With<TargetHealthBar>>,
+) {
+    let Ok(mut visibility) = frame.get_single_mut() else {
+        return;
+    };
+
+    let Ok((selection, character, health, experience, id, speed)) = selected.get_single() else {
+        visibility.set_if_neq(Visibility::Hidden);
+        return;
+    };
+
+    visibility.set_if_neq(Visibility::Inherited);
+
+    let changed = selection.is_added()
+        || inspecting.is_changed()
+        || health.is_changed()
+        || experience.is_changed()
+        || speed.is_changed();
+
+    if !changed {
+        return;
+    }
+
+    for (kind, mut text, mut node) in &mut texts {
+        let value = match kind {
+            TargetText::Name => character.as_str().into(),
+            TargetText::Level => format!("Level {}", experience.level),
+            TargetText::Health => format!("{} / {}", health.current, health.maximum),
+            TargetText::Details => format!(
+                "Account {}\nExperience {}\nSpeed {} / {}",
+                id.0,
+                experience.current,
+                speed.walking,
+                speed.running
+            ),
+        };
+
+        text.0 = value;
+
+        // details only take up space once the character is inspected
+        if *kind == TargetText::Details {
+            node.display = if inspecting.0 { Display::Flex } else { Display::None };
+        }
+    }
+
+    for mut node in &mut bar {
+        node.width = Val::Percent(health.fraction() * 100.);
+    }
+}
+
+fn draw_selection(
+    mut gizmos: Gizmos,
+    query: Query<&GlobalTransform, With<Selected>>,
+) {
+    for transform in &query {
+        let feet = transform.translation().truncate() - Vec2::new(0., FEET_OFFSET);
+        gizmos.ellipse_2d(Isometry2d::from_translation(feet), RING_SIZE, GOLD);
+    }
+}
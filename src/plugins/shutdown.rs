@@ -0,0 +1,136 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+use async_channel::{Receiver, Sender};
+use async_std::task::sleep;
+use bevy::prelude::*;
+use bevy::tasks::{block_on, IoTaskPool, Task};
+use bevy::window::WindowCloseRequested;
+use futures_util::future::{select, Either};
+use futures_util::pin_mut;
+
+// how long to wait for all tasks to stop when the app exits
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+// a signal shared between the app and a background task. Cancelling
+// closes the underlying channel, which wakes every clone at once.
+#[derive(Clone, Debug)]
+pub struct CancellationToken {
+    sender: Sender<()>,
+    receiver: Receiver<()>,
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        let (sender, receiver) = async_channel::bounded(1);
+        Self { sender, receiver }
+    }
+}
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.sender.close();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.receiver.is_closed()
+    }
+
+    // resolves once `cancel` has been called
+    pub async fn cancelled(&self) {
+        // nothing is ever sent, so this only returns on close
+        self.receiver.recv().await.ok();
+    }
+}
+
+struct TrackedTask {
+    name: String,
+    token: CancellationToken,
+    task: Task<()>,
+}
+
+// owns every long-running network task so they can be stopped together
+#[derive(Resource, Default)]
+pub struct ShutdownCoordinator {
+    tasks: Vec<TrackedTask>,
+    finished: bool,
+}
+
+pub struct ShutdownPlugin;
+
+impl Plugin for ShutdownPlugin {
+    fn build(&self, app: &mut App) {
+        // registered here too so the plugin works without a window
+        app
+            .add_event::<WindowCloseRequested>()
+            .init_resource::<ShutdownCoordinator>()
+            .add_systems(Last, reap_tasks)
+            .add_systems(PostUpdate, shutdown);
+    }
+}
+
+impl ShutdownCoordinator {
+
+    // spawn a task on the IO pool, passing it the token that stops it
+    pub fn spawn<F, Fut>(&mut self, name: impl Into<String>, func: F) -> CancellationToken
+    where
+        F: FnOnce(CancellationToken) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let token = CancellationToken::default();
+        let task = IoTaskPool::get().spawn(func(token.clone()));
+
+        self.tasks.push(TrackedTask {
+            name: name.into(),
+            token: token.clone(),
+            task,
+        });
+
+        token
+    }
+
+    // cancel every task and block until they stop or `timeout` passes
+    pub fn shutdown(&mut self, timeout: Duration) {
+        if self.finished {
+            return;
+        }
+
+        self.finished = true;
+
+        for tracked in self.tasks.iter() {
+            tracked.token.cancel();
+        }
+
+        let deadline = Instant::now() + timeout;
+
+        for tracked in self.tasks.drain(..) {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let timer = sleep(remaining);
+            let task = tracked.task;
+
+            pin_mut!(timer);
+
+            match block_on(select(task, timer)) {
+                Either::Left(_) => debug!("Task '{}' stopped", tracked.name),
+                Either::Right(_) => error!("Task '{}' did not stop within {:?}", tracked.name, timeout),
+            }
+        }
+    }
+}
+
+// forget about tasks that have already finished
+fn reap_tasks(mut coordinator: ResMut<ShutdownCoordinator>) {
+    coordinator.tasks.retain(|t| !t.task.is_finished());
+}
+
+fn shutdown(
+    mut exits: EventReader<AppExit>,
+    mut closes: EventReader<WindowCloseRequested>,
+    mut coordinator: ResMut<ShutdownCoordinator>,
+) {
+    let exit_count = exits.read().count();
+    let close_count = closes.read().count();
+
+    if exit_count > 0 || close_count > 0 {
+        coordinator.shutdown(SHUTDOWN_TIMEOUT);
+    }
+}
@@ -1,5 +1,12 @@
-use reqwest::StatusCode;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::OnceLock;
+use std::task::{Context, Poll};
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
 use serde::{Serialize,Deserialize};
+use tokio::runtime::Runtime;
+use tokio::task::JoinHandle;
+use crate::config::ClientConfig;
 use crate::errors::{Error, Result};
 
 #[derive(Serialize,Deserialize,Debug)]
@@ -28,50 +35,182 @@ pub struct AccountKey {
     pub token: String,
 }
 
-pub fn register<T: ToString>(username: T, password1: T, password2: T) -> Result<AccountInfo> {
-    const URL: &str = "http://localhost:8080/register";
+// the body the server sends with a failed request
+#[derive(Serialize,Deserialize,Debug,Default)]
+pub struct ErrorResponse {
+    #[serde(default)]
+    pub code: Option<String>,
+    #[serde(default)]
+    pub field: Option<String>,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+// a request running in the background. Awaiting it gives the result,
+// and dropping it aborts the request.
+pub struct Request<T>(JoinHandle<T>);
+
+// the request a failed response came from
+#[derive(Clone,Copy,PartialEq,Debug)]
+pub enum Endpoint {
+    Register,
+    Login,
+    Validate,
+}
+
+// reqwest needs a tokio runtime, which bevy's task pools don't provide,
+// so requests get one of their own
+fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("tinker-http")
+            .enable_all()
+            .build()
+            .expect("Could not start the HTTP runtime")
+    })
+}
+
+// start a request in the background. The future has to own what it
+// uses, so clone the config into an `async move` block first.
+pub fn spawn<F>(future: F) -> Request<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    Request(runtime().spawn(future))
+}
+
+impl<T> Future for Request<T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<T> {
+        Pin::new(&mut self.0).poll(context).map(|result| match result {
+            Ok(value) => value,
+            Err(error) => std::panic::resume_unwind(error.into_panic()),
+        })
+    }
+}
+
+impl<T> Drop for Request<T> {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+fn client(config: &ClientConfig) -> Result<Client> {
+    Ok(Client::builder()
+        .timeout(config.request_timeout())
+        .build()?)
+}
+
+async fn send(request: RequestBuilder) -> Result<Response> {
+    request.send().await.map_err(|e| {
+        if e.is_connect() || e.is_timeout() {
+            Error::ServerUnreachable
+        } else {
+            e.into()
+        }
+    })
+}
+
+async fn read_failure(endpoint: Endpoint, response: Response) -> Error {
+    let status = response.status();
+    let text = response.text().await.unwrap_or_default();
+    failure(endpoint, status, &text)
+}
+
+// picks the error for a failed response from its status and body. A body
+// that isn't an `ErrorResponse` is ignored.
+pub fn failure(endpoint: Endpoint, status: StatusCode, text: &str) -> Error {
+    let body: ErrorResponse = serde_json::from_str(text).unwrap_or_default();
+
+    let code = body.code.as_deref().unwrap_or_default();
+    let reason = body.reason
+        .clone()
+        .or_else(|| status.canonical_reason().map(String::from))
+        .unwrap_or_else(|| status.to_string());
+
+    match (endpoint, status, code) {
+        (_, StatusCode::TOO_MANY_REQUESTS, _) | (_, _, "rate_limited") => 
+            Error::RateLimited,
+        (_, StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT, _) =>
+            Error::ServerUnreachable,
+        (Endpoint::Register, StatusCode::CONFLICT, _) | (_, _, "username_taken") => 
+            Error::UsernameTaken,
+        (Endpoint::Register, _, "password_mismatch") => 
+            Error::PasswordMismatch,
+        (Endpoint::Login | Endpoint::Validate, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN | StatusCode::NOT_FOUND, _) | (_, _, "bad_credentials") => 
+            Error::BadCredentials,
+        _ => match (body.field, endpoint) {
+            (Some(field), _) => Error::InvalidField { field, reason },
+            (None, Endpoint::Register) => Error::RegisterFailed(reason),
+            (None, Endpoint::Login | Endpoint::Validate) => Error::LoginFailed(reason),
+        }
+    }
+}
+
+pub async fn register<T: ToString>(config: &ClientConfig, username: T, password1: T, password2: T) -> Result<AccountInfo> {
+    let url = config.http_url("/register");
 
     let username = username.to_string();
     let password1 = password1.to_string();
     let password2 = password2.to_string();
     
-    let client = reqwest::blocking::Client::new();
+    let client = client(config)?;
     
-    let response = client.post(URL)
+    let response = send(client.post(url)
         .json(&RegisterForm {
             username,
             password1,
             password2
-        })
-        .send()?;
+        })).await?;
 
     if response.status().is_success() {
-        let text = response.text()?;
+        let text = response.text().await?;
         Ok(serde_json::from_slice(text.as_bytes())?)
     } else {
-        Err(Error::RegisterFailed)
+        Err(read_failure(Endpoint::Register, response).await)
     }
 }
 
-pub fn login<T: ToString>(username: T, password: T) -> Result<AccountKey> {
-    const URL: &str = "http://localhost:8080/login";
+pub async fn login<T: ToString>(config: &ClientConfig, username: T, password: T) -> Result<AccountKey> {
+    let url = config.http_url("/login");
 
     let username = username.to_string();
     let password = password.to_string();
     
-    let client = reqwest::blocking::Client::new();
+    let client = client(config)?;
     
-    let response = client.get(URL)
+    let response = send(client.get(url)
         .json(&LoginForm {
             username,
             password,
-        })
-        .send()?;
+        })).await?;
 
     if response.status().is_success() {
-        let text = response.text()?;
+        let text = response.text().await?;
         Ok(serde_json::from_slice(text.as_bytes())?)
     } else {
-        Err(Error::LoginFailed)
+        Err(read_failure(Endpoint::Login, response).await)
     }
-}
\ No newline at end of file
+}
+
+// checks that a saved session token is still accepted by the server
+pub async fn validate<T: ToString>(config: &ClientConfig, token: T) -> Result<AccountInfo> {
+    let url = config.http_url("/validate");
+    let token = token.to_string();
+
+    let client = client(config)?;
+
+    let response = send(client.get(url)
+        .bearer_auth(token)).await?;
+
+    if response.status().is_success() {
+        let text = response.text().await?;
+        Ok(serde_json::from_slice(text.as_bytes())?)
+    } else {
+        Err(read_failure(Endpoint::Validate, response).await)
+    }
+}
@@ -0,0 +1,121 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use bevy::prelude::*;
+use serde::{Serialize,Deserialize};
+
+use crate::errors::{Error, Result};
+use crate::state::ConnectionState;
+use crate::views::ViewState;
+
+const SESSION_FILE: &str = "session.json";
+
+// end the current session and forget any saved token
+#[derive(Event, Debug, Default)]
+pub struct Logout;
+
+// a session token saved between launches when "Remember me" is checked
+#[derive(Serialize,Deserialize,Debug,Clone)]
+pub struct SavedSession {
+    pub id: i32,
+    pub username: String,
+    pub token: String,
+}
+
+pub struct SessionPlugin;
+
+impl Plugin for SessionPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<Logout>()
+            .add_systems(Update, logout.run_if(on_event::<Logout>));
+    }
+}
+
+impl SavedSession {
+
+    // e.g. `~/.local/share/tinker/session.json` on linux
+    pub fn path() -> Result<PathBuf> {
+        dirs::data_local_dir()
+            .map(|p| p.join("tinker").join(SESSION_FILE))
+            .ok_or(Error::NoDataDirectory)
+    }
+
+    pub fn load() -> Result<Option<Self>> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        let text = fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str(&text)?))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path()?;
+
+        if let Some(parent) = path.parent() {
+            Self::create_dir(parent)?;
+        }
+
+        let mut file = Self::create_file(&path)?;
+        file.write_all(serde_json::to_string(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn delete() -> Result<()> {
+        let path = Self::path()?;
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    // the token is a credential, so only the current user may read it
+    #[cfg(unix)]
+    fn create_file(path: &Path) -> Result<fs::File> {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)?;
+        // mode only applies to new files
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        Ok(file)
+    }
+
+    #[cfg(not(unix))]
+    fn create_file(path: &Path) -> Result<fs::File> {
+        Ok(fs::File::create(path)?)
+    }
+
+    #[cfg(unix)]
+    fn create_dir(path: &Path) -> Result<()> {
+        use std::os::unix::fs::DirBuilderExt;
+        Ok(fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(path)?)
+    }
+
+    #[cfg(not(unix))]
+    fn create_dir(path: &Path) -> Result<()> {
+        Ok(fs::create_dir_all(path)?)
+    }
+}
+
+fn logout(
+    mut events: EventReader<Logout>,
+    mut state: ResMut<ConnectionState>,
+    mut view_state: ResMut<NextState<ViewState>>,
+) {
+    events.clear();
+
+    if let Err(e) = SavedSession::delete() {
+        warn!("Could not delete saved session: {}", e);
+    }
+
+    *state = ConnectionState::default();
+    view_state.set(ViewState::Menu);
+}
@@ -0,0 +1,46 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// a change to a character's stats. Like chat, `tinker_records` has no
+// message for these, so they are sent next to its messages.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StatMessage {
+    pub account_id: i32,
+    pub change: StatChange,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum StatChange {
+    Health { current: usize, maximum: usize },
+    // added to the current level's experience
+    Experience { gained: usize },
+    // the server decides when a level is reached, and sends what is
+    // left over towards the next one and how much the next one needs
+    Level { level: usize, experience: usize, required: usize },
+    // speed tiers, see `Speed`
+    Speed { walking: usize, running: usize },
+}
+
+impl StatMessage {
+    pub fn new(account_id: i32, change: StatChange) -> Self {
+        Self {
+            account_id,
+            change,
+        }
+    }
+}
+
+// a character reached a new level
+#[derive(Event, Debug, Clone, Copy)]
+pub struct LevelUp {
+    pub entity: Entity,
+    pub level: usize,
+}
+
+// a character lost health
+#[derive(Event, Debug, Clone, Copy)]
+pub struct Damaged {
+    pub entity: Entity,
+    pub amount: usize,
+    pub remaining: usize,
+}
@@ -0,0 +1,98 @@
+pub const USERNAME_MIN: usize = 3;
+pub const USERNAME_MAX: usize = 20;
+pub const PASSWORD_MIN: usize = 8;
+pub const PASSWORD_MAX: usize = 64;
+
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Invalid {
+    #[error("Username is required")]
+    UsernameMissing,
+
+    #[error("Username must be {}-{} characters", USERNAME_MIN, USERNAME_MAX)]
+    UsernameLength,
+
+    #[error("Username may only use letters, numbers, '_' and '-'")]
+    UsernameCharacters,
+
+    #[error("Password is required")]
+    PasswordMissing,
+
+    #[error("Password must be {}-{} characters", PASSWORD_MIN, PASSWORD_MAX)]
+    PasswordLength,
+
+    #[error("Password needs a letter and a number")]
+    PasswordWeak,
+
+    #[error("Passwords do not match")]
+    PasswordMismatch,
+}
+
+// the problems found with each field of the registration form
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RegisterErrors {
+    pub username: Option<Invalid>,
+    pub password1: Option<Invalid>,
+    pub password2: Option<Invalid>,
+}
+
+impl RegisterErrors {
+    pub fn is_valid(&self) -> bool {
+        self.username.is_none() &&
+        self.password1.is_none() &&
+        self.password2.is_none()
+    }
+}
+
+pub fn validate_username(username: &str) -> Option<Invalid> {
+    let length = username.chars().count();
+
+    if length == 0 {
+        Some(Invalid::UsernameMissing)
+    }
+    else if !(USERNAME_MIN..=USERNAME_MAX).contains(&length) {
+        Some(Invalid::UsernameLength)
+    }
+    else if !username.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        Some(Invalid::UsernameCharacters)
+    }
+    else {
+        None
+    }
+}
+
+pub fn validate_password(password: &str) -> Option<Invalid> {
+    let length = password.chars().count();
+
+    if length == 0 {
+        Some(Invalid::PasswordMissing)
+    }
+    else if !(PASSWORD_MIN..=PASSWORD_MAX).contains(&length) {
+        Some(Invalid::PasswordLength)
+    }
+    else if !password.chars().any(|c| c.is_alphabetic()) || !password.chars().any(|c| c.is_numeric()) {
+        Some(Invalid::PasswordWeak)
+    }
+    else {
+        None
+    }
+}
+
+pub fn validate_confirmation(password1: &str, password2: &str) -> Option<Invalid> {
+    if password2.is_empty() {
+        Some(Invalid::PasswordMissing)
+    }
+    else if password1 != password2 {
+        Some(Invalid::PasswordMismatch)
+    }
+    else {
+        None
+    }
+}
+
+pub fn validate_register(username: &str, password1: &str, password2: &str) -> RegisterErrors {
+    RegisterErrors {
+        username: validate_username(username),
+        password1: validate_password(password1),
+        password2: validate_confirmation(password1, password2),
+    }
+}
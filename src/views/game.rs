@@ -1,199 +1,236 @@
-use std::collections::VecDeque;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Barrier;
-use std::time::Duration;
 use bevy::input::mouse::{AccumulatedMouseScroll, MouseMotion};
+use bevy::color::palettes::css::FIRE_BRICK;
+use bevy::ui::FocusPolicy;
 use bevy::window::PrimaryWindow;
 use bevy_ecs_tiled::prelude::*;
 use bevy::prelude::*;
-use futures_util::future::{select, Either};
-use async_std::task::sleep;
-use futures_util::lock::Mutex;
-use futures_util::pin_mut;
-use once_cell::sync::Lazy;
-use tinker_records::messages::{Message, Value};
-use async_tungstenite::async_std::connect_async;
-use tungstenite as ts;
-use futures_util::stream::StreamExt;
+use tinker_records::messages::Value;
 
 use crate::cursor::{Cursor, CursorData, CursorType};
-use crate::player::{AccountId, CharacterType, Direction, EntityType, Graphic, Player, PlayerType, Speed, Target};
+use crate::plugins::animation::AnimationSetPlugin;
+use crate::plugins::button::{MyButton, MyButtonLabel};
+use crate::player::{AccountId, CharacterType, EntityType, Experience, Health, Player, PlayerType, Speed, Target};
+use crate::plugins::network::{ConnectionStatus, Incoming, IncomingStats, Outgoing};
+use crate::plugins::interpolation::{InterpolationPlugin, Snapshot, SnapshotBuffer};
+use crate::plugins::chat::{chat_inactive, ChatPlugin};
+use crate::plugins::follow::{Follow, FollowPlugin};
+use crate::plugins::hud::HudPlugin;
+use crate::plugins::nameplate::NameplatePlugin;
+use crate::plugins::movement::MovementPlugin;
+use crate::plugins::pathfinding::{ChooseTarget, PathfindingPlugin, Walkability};
+use crate::plugins::prediction::PredictionPlugin;
+use crate::plugins::scheduler::SchedulerPlugin;
+use crate::plugins::selection::{PickingSet, SelectionPlugin};
+use crate::session::Logout;
 use crate::state::ConnectionState;
-use bevy::tasks::IoTaskPool;
+use crate::stats::{Damaged, LevelUp, StatChange};
 
 use super::{despawn_view, ViewState};
 
-pub static RUNNING: AtomicBool = AtomicBool::new(false);
-pub static SHUTDOWN: AtomicBool = AtomicBool::new(false);
-pub static SHUTDOWN_BARRIER: Barrier = Barrier::new(2);
-
-pub static INCOMING_QUEUE: Lazy<Mutex<VecDeque<Message>>> = Lazy::new(|| { Default::default() });
-pub static OUTGOING_QUEUE: Lazy<Mutex<VecDeque<Message>>> = Lazy::new(|| { Default::default() });
-
 #[derive(Component)]
 pub struct OnGame;
 
-pub fn main_game(app: &mut App) {
-    app
-        .add_systems(OnEnter(ViewState::Game), socket_connection)
-        .add_systems(OnEnter(ViewState::Game), handle_messages)
-        .add_systems(Update, process_messages)
-
-        .add_systems(OnEnter(ViewState::Game), game_setup)
-        .add_systems(OnExit(ViewState::Game), despawn_view::<OnGame>)
-
-        .add_systems(Update, character_movement.run_if(in_state(ViewState::Game)))
-
-        .add_systems(Update, player_movement.run_if(in_state(ViewState::Game)))
-        .add_systems(Update, camera_movement.run_if(in_state(ViewState::Game)))
-        .add_systems(Update, player_animation.run_if(in_state(ViewState::Game)))
-        .add_systems(Update, cursor_movement.run_if(in_state(ViewState::Game)))
-        .add_systems(Update, cursor_animation.run_if(in_state(ViewState::Game)))
-        .add_systems(Update, camera_zoom.run_if(in_state(ViewState::Game)));
-}
+#[derive(Component)]
+struct ConnectionBanner;
 
-fn broadcast(message: Message) {
-    // TODO: change this to use an mpsc channel to enqueue outgoing messages
-    // TODO: change the socket_connection_task to use mpsc channel to send incoming messages
-    if let Some(mut queue) = OUTGOING_QUEUE.try_lock() {
-        queue.push_back(message);
-    }
+#[derive(Component)]
+struct EscapeMenu;
+
+// whether the escape menu is showing
+#[derive(Resource, Default)]
+struct EscapeMenuOpen(bool);
+
+#[derive(Component, Default)]
+enum GameButtonAction {
+    #[default]
+    Resume,
+    Logout,
+    ReturnToMenu,
+    Quit,
 }
 
+const NORMAL_BUTTON: Color = Color::srgb(1.0, 0.84, 0.0);
+const HOVERED_BUTTON: Color = Color::srgb(1.0, 0.92, 0.5);
 
-#[derive(Resource)]
-struct ConnectionChannel {
-    receiver: std::sync::Mutex<std::sync::mpsc::Receiver<Message>>,
+// the parts of the game that don't need a window, shared with headless mode
+pub fn game_logic(app: &mut App) {
+    app
+        .add_plugins(AnimationSetPlugin)
+        .add_plugins(PredictionPlugin)
+        .add_plugins(MovementPlugin)
+        .add_plugins(InterpolationPlugin)
+        .add_plugins(PathfindingPlugin)
+        .add_plugins(FollowPlugin)
+        .add_plugins(SchedulerPlugin)
+        .add_event::<LevelUp>()
+        .add_event::<Damaged>()
+        // stats can arrive in the same frame as the character they're for
+        .add_systems(Update, (process_messages, process_stats)
+            .chain()
+            .run_if(in_state(ViewState::Game)));
 }
 
-fn handle_messages(
-    mut commands: Commands,
-) {
-    let (sender, receiver) = std::sync::mpsc::channel();
-    IoTaskPool::get().spawn(handle_messages_task(sender)).detach();
-    commands.insert_resource(ConnectionChannel { receiver: std::sync::Mutex::new(receiver) });
-}
+pub fn main_game(app: &mut App) {
+    app
+        .add_plugins(game_logic)
+        .add_plugins(SelectionPlugin)
+        .add_plugins(ChatPlugin)
+        .add_plugins(HudPlugin)
+        .add_plugins(NameplatePlugin)
 
-fn socket_connection(
-    state: Res<ConnectionState>,
-) {
-    IoTaskPool::get().spawn(socket_connection_task(state.clone())).detach();
-}
+        .init_resource::<EscapeMenuOpen>()
+        .configure_sets(Update, PickingSet.run_if(escape_menu_closed))
 
-async fn handle_messages_task(
-    sender: std::sync::mpsc::Sender<Message>
-) {
-    loop {
-        for item in INCOMING_QUEUE.lock().await.drain(..) {
-            sender.send(item).unwrap();
-        }
-    }
+        .add_systems(OnEnter(ViewState::Game), game_setup)
+        .add_systems(OnExit(ViewState::Game), (
+            despawn_view::<OnGame>,
+            reset_escape_menu
+        ))
+
+        .add_systems(Update, player_speed
+            .run_if(in_state(ViewState::Game))
+            .run_if(chat_inactive))
+        .add_systems(Update, player_movement
+            .in_set(ChooseTarget)
+            .after(PickingSet)
+            .run_if(in_state(ViewState::Game))
+            .run_if(escape_menu_closed))
+        .add_systems(Update, camera_movement.run_if(in_state(ViewState::Game)))
+        .add_systems(Update, cursor_movement
+            .after(PickingSet)
+            .run_if(in_state(ViewState::Game))
+            .run_if(escape_menu_closed))
+        .add_systems(Update, cursor_animation.run_if(in_state(ViewState::Game)))
+        .add_systems(Update, camera_zoom.run_if(in_state(ViewState::Game)))
+        .add_systems(Update, (
+            toggle_escape_menu.run_if(chat_inactive),
+            escape_menu_visibility.run_if(resource_changed::<EscapeMenuOpen>),
+            game_button_system,
+            game_action
+        )
+            .chain()
+            .run_if(in_state(ViewState::Game)))
+        .add_systems(Update, connection_banner
+            .run_if(in_state(ViewState::Game))
+            .run_if(resource_changed::<ConnectionStatus>));
 }
 
 fn process_messages(
     mut query: Query<(
         &AccountId,
-        &mut Speed,
-        &mut Target,
+        &mut SnapshotBuffer,
     ),With<CharacterType>>,
     delete_query: Query<(Entity,&AccountId), With<CharacterType>>,
-    channel: Option<Res<ConnectionChannel>>,
+    mut incoming: EventReader<Incoming>,
     mut commands: Commands,
+    time: Res<Time<Real>>,
     asset_server: Res<AssetServer>,
-    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
 ) {
-    if let Some(reader) = channel {
-        if let Ok(rx) = reader.receiver.lock() {
-            while let Ok(item) = rx.try_recv() {
-                match item.value {
-                    Value::Move(message) => {
-                        for (id, mut speed, mut target) in &mut query {
-                            if id.0 == item.header.account_id {
-                                target.0 = Some(message.target);
-                                speed.fixed = Some(message.speed);
-                                break;
-                            }
-                        }
-                    },
-                    Value::Initial(message) => {
-                        for character in message.entities {
-                            Player::new::<CharacterType>(
-                                character.id,
-                                &asset_server,
-                                &mut texture_atlas_layouts
-                            )
-                            .with_name(character.username.clone())
-                            .with_position(character.x, character.y, 2.0)
-                            .with_speed(0.0)
-                            .build(&mut commands);
-                        }
-                    },
-                    Value::Connect(message) => {
-                        Player::new::<CharacterType>(
-                            item.header.account_id,
-                            &asset_server,
-                            &mut texture_atlas_layouts
-                        )
-                        .with_name(message.entity.username.clone())
-                        .with_position(message.entity.x, message.entity.y, 2.0)
-                        .with_speed(0.0)
-                        .build(&mut commands);
-                    },
-                    Value::Disconnect(_) => {
-                        for (entity, id) in &delete_query {
-                            if id.0 == item.header.account_id {
-                                commands.entity(entity).despawn_recursive();
-                                break;
-                            }
-                        }
-                    },
-                    _ => ()
+    let now = time.elapsed_secs_f64();
+
+    for Incoming(item) in incoming.read() {
+        match &item.value {
+            Value::Move(message) => {
+                for (id, mut buffer) in &mut query {
+                    if id.0 == item.header.account_id {
+                        buffer.push(Snapshot {
+                            time: now,
+                            position: message.position,
+                            target: message.target,
+                            speed: message.speed,
+                        });
+                        break;
+                    }
                 }
-            }
+            },
+            Value::Initial(message) => {
+                // a resync replaces whatever characters we already know about
+                for (entity, _) in &delete_query {
+                    commands.entity(entity).despawn_recursive();
+                }
+
+                for character in message.entities.iter() {
+                    spawn_character(&mut commands, &asset_server, character.id, &character.username, character.x, character.y, now);
+                }
+            },
+            Value::Connect(message) => {
+                let entity = &message.entity;
+                spawn_character(&mut commands, &asset_server, item.header.account_id, &entity.username, entity.x, entity.y, now);
+            },
+            Value::Disconnect(_) => {
+                for (entity, id) in &delete_query {
+                    if id.0 == item.header.account_id {
+                        commands.entity(entity).despawn_recursive();
+                        break;
+                    }
+                }
+            },
+            _ => ()
         }
     }
 }
 
-async fn socket_connection_task(
-    state: ConnectionState
+// the buffer is spawned with the character, so a move arriving in the
+// next frame already has somewhere to go
+fn spawn_character(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    id: i32,
+    username: &str,
+    x: f32,
+    y: f32,
+    time: f64,
 ) {
-    if let Some(token) = state.token {
-        let url = format!("ws://localhost:8080/connect/{}",token);
-    
-        let (mut stream, _) = connect_async(&url)
-            .await
-            .expect("Failed to connect");
-
-        RUNNING.store(true, Ordering::Relaxed);
-
-        loop {
-            // create timeout and stream futures
-            let timeout = sleep(Duration::from_millis(100));
-            let source = stream.next();
-
-            pin_mut!(timeout);
-
-            // check for incoming messages from the server
-            if let Either::Left((result, _)) = select(source,timeout).await {
-                if let Some(Ok(ts::Message::Text(value))) = result {
-                    let message = serde_json::from_slice(value.as_bytes()).unwrap();
-                    INCOMING_QUEUE.lock().await.push_back(message);
-                }
-            }
+    let character = Player::new::<CharacterType>(id, asset_server)
+        .with_name(username.to_string())
+        .with_position(x, y, 2.0)
+        .with_speed(0.0);
 
-            // send outgoing messages to the server
-            while let Some(value) = OUTGOING_QUEUE.lock().await.pop_front() {
-                let message = serde_json::to_string(&value).unwrap();
-                stream.send(ts::Message::text(message)).await.unwrap();
-            }
+    commands.spawn((character, SnapshotBuffer::starting_at(time, Vec3::new(x, y, 2.0))));
+}
 
-            if SHUTDOWN.load(Ordering::Relaxed) {
-                println!("SHUTTING DOWN");
-                stream.close(None).await.unwrap();
-                SHUTDOWN_BARRIER.wait();
-                break;
-            }
+fn process_stats(
+    mut query: Query<(Entity, &AccountId, &mut Health, &mut Experience, &mut Speed), With<EntityType>>,
+    mut incoming: EventReader<IncomingStats>,
+    mut level_up: EventWriter<LevelUp>,
+    mut damaged: EventWriter<Damaged>,
+) {
+    for IncomingStats(message) in incoming.read() {
+        let Some((entity, _, mut health, mut experience, mut speed)) = query
+            .iter_mut()
+            .find(|(_, id, ..)| id.0 == message.account_id) else {
+            continue;
+        };
 
+        match message.change {
+            StatChange::Health { current, maximum } => {
+                if current < health.current {
+                    damaged.send(Damaged {
+                        entity,
+                        amount: health.current - current,
+                        remaining: current,
+                    });
+                }
+                health.current = current.min(maximum);
+                health.maximum = maximum;
+            },
+            StatChange::Experience { gained } => {
+                experience.current += gained;
+            },
+            StatChange::Level { level, experience: current, required } => {
+                if level > experience.level {
+                    level_up.send(LevelUp { entity, level });
+                }
+                experience.level = level;
+                experience.current = current;
+                experience.required = Some(required);
+            },
+            // the local player picks its tier from these every frame,
+            // and other characters move at whatever speed they send
+            StatChange::Speed { walking, running } => {
+                speed.walking = walking;
+                speed.running = running;
+            },
         }
     }
 }
@@ -220,7 +257,6 @@ fn game_setup(
     Player::new::<PlayerType>(
         state.id,
         &asset_server,
-        &mut texture_atlas_layouts
     )
     .with_name(state.username.clone())
     .build(&mut commands);
@@ -230,6 +266,190 @@ fn game_setup(
         &mut texture_atlas_layouts
     ), OnGame));
 
+    let banner_wrapper = (
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            width: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        OnGame
+    );
+
+    let banner = (
+        Text::new(""),
+        TextFont {
+            font_size: 20.0,
+            ..default()
+        },
+        TextColor(Color::srgb(0.9, 0.9, 0.9)),
+        Node {
+            padding: UiRect::all(Val::Px(10.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
+        Visibility::Hidden,
+        ConnectionBanner
+    );
+
+    commands
+        .spawn(banner_wrapper)
+        .with_child(banner);
+
+    let menu_background = (
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
+        FocusPolicy::Block,
+        Visibility::Hidden,
+        EscapeMenu,
+        OnGame
+    );
+
+    let menu_dialog = (
+        Node {
+            width: Val::Px(300.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(10.0),
+            padding: UiRect::all(Val::Px(20.0)),
+            ..default()
+        },
+        BackgroundColor(FIRE_BRICK.into()),
+    );
+
+    commands
+        .spawn(menu_background)
+        .with_children(|parent| {
+            parent
+                .spawn(menu_dialog)
+                .with_children(|parent| {
+                    menu_button(parent, "Resume", GameButtonAction::Resume);
+                    menu_button(parent, "Logout", GameButtonAction::Logout);
+                    menu_button(parent, "Return to Menu", GameButtonAction::ReturnToMenu);
+                    menu_button(parent, "Quit", GameButtonAction::Quit);
+                });
+        });
+}
+
+fn menu_button(parent: &mut ChildBuilder<'_>, label: &str, action: GameButtonAction) {
+    let mut button = MyButton::new(action);
+    button.background_color = NORMAL_BUTTON.into();
+
+    parent
+        .spawn(button)
+        .with_child(MyButtonLabel::new(label));
+}
+
+fn escape_menu_closed(menu: Res<EscapeMenuOpen>) -> bool {
+    !menu.0
+}
+
+fn reset_escape_menu(mut menu: ResMut<EscapeMenuOpen>) {
+    menu.0 = false;
+}
+
+fn toggle_escape_menu(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut menu: ResMut<EscapeMenuOpen>,
+) {
+    if keys.just_pressed(KeyCode::Escape) {
+        menu.0 = !menu.0;
+    }
+}
+
+fn escape_menu_visibility(
+    menu: Res<EscapeMenuOpen>,
+    mut query: Query<&mut Visibility, With<EscapeMenu>>,
+) {
+    for mut visibility in &mut query {
+        *visibility = if menu.0 {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn game_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>, With<GameButtonAction>),
+    >,
+) {
+    for (interaction, mut background_color) in &mut interaction_query {
+        *background_color = match *interaction {
+            Interaction::Pressed | Interaction::Hovered => HOVERED_BUTTON.into(),
+            Interaction::None => NORMAL_BUTTON.into()
+        }
+    }
+}
+
+fn game_action(
+    interaction_query: Query<
+        (&Interaction, &GameButtonAction),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut menu: ResMut<EscapeMenuOpen>,
+    mut state: ResMut<ConnectionState>,
+    mut view_state: ResMut<NextState<ViewState>>,
+    mut logout: EventWriter<Logout>,
+    mut outgoing: EventWriter<Outgoing>,
+    mut app_exit_events: EventWriter<AppExit>,
+) {
+    for (interaction, action) in &interaction_query {
+        if *interaction == Interaction::Pressed {
+            match action {
+                GameButtonAction::Resume => {
+                    menu.0 = false;
+                },
+                GameButtonAction::Logout => {
+                    outgoing.send(Outgoing::disconnect(state.id));
+                    logout.send(Logout);
+                },
+                GameButtonAction::ReturnToMenu => {
+                    // the connection is closed when the game view exits,
+                    // after this has been sent
+                    outgoing.send(Outgoing::disconnect(state.id));
+                    *state = ConnectionState::default();
+                    view_state.set(ViewState::Menu);
+                },
+                GameButtonAction::Quit => {
+                    app_exit_events.send(AppExit::Success);
+                }
+            }
+        }
+    }
+}
+
+fn connection_banner(
+    status: Res<ConnectionStatus>,
+    mut query: Query<(&mut Text, &mut Visibility), With<ConnectionBanner>>,
+) {
+    for (mut text, mut visibility) in &mut query {
+        *visibility = match *status {
+            ConnectionStatus::Reconnecting(attempt) => {
+                text.0 = format!("Reconnecting… (attempt {})", attempt);
+                Visibility::Inherited
+            },
+            ConnectionStatus::Failed => {
+                text.0 = "Connection lost".into();
+                Visibility::Inherited
+            },
+            ConnectionStatus::Closed => {
+                text.0 = "Disconnected by the server".into();
+                Visibility::Inherited
+            },
+            ConnectionStatus::Connecting | ConnectionStatus::Connected => Visibility::Hidden,
+        };
+    }
 }
 
 fn camera_zoom(
@@ -263,38 +483,6 @@ fn cursor_animation(
     }
 }
 
-fn player_animation(
-    time: Res<Time>, 
-    keys: Res<ButtonInput<KeyCode>>, 
-    mut query: Query<(
-        &mut Graphic,
-        &mut Sprite,
-        &mut Target,
-        &mut Direction
-    ),With<EntityType>>,
-) {
-    for (mut graphic, mut sprite, target, direction) in &mut query {
-        graphic.timer.tick(time.delta());
-        
-        let animation = if target.0.is_none() {
-            &graphic.idle
-        } else {
-            if keys.pressed(KeyCode::ShiftLeft) {
-                &graphic.running
-            } else {
-                &graphic.walking
-            }
-        };
-
-        if graphic.timer.just_finished() {
-            if let Some(atlas) = &mut sprite.texture_atlas {
-                atlas.index = animation.next(*direction, atlas.index);
-            }
-            graphic.reset();
-        }
-    }
-}
-
 fn cursor_movement(
     mut query: Query<(
         &mut Sprite,
@@ -342,74 +530,63 @@ fn camera_movement(
 
 }
 
+// the player runs while shift is held
+fn player_speed(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut query: Query<&mut Speed, With<PlayerType>>,
+) {
+    let Ok(mut speed) = query.get_single_mut() else {
+        return;
+    };
+
+    let tier = if keys.pressed(KeyCode::ShiftLeft) {
+        speed.running
+    } else {
+        speed.walking
+    };
+
+    if speed.fixed != Some(tier as f32) {
+        speed.fixed = Some(tier as f32);
+    }
+}
+
+// holding the button steers toward the cursor, but a new route is only
+// planned when it moves onto another tile. Menus and the chat box reset
+// the button when they take a click, so the player stays where they are.
 fn player_movement(
-    mut query: Query<(
-        &mut Speed,
-        &mut Target
-    ),With<PlayerType>>,
-    keys: Res<ButtonInput<KeyCode>>, 
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Target), With<PlayerType>>,
     buttons: Res<ButtonInput<MouseButton>>,
     windows: Query<&Window, With<PrimaryWindow>>,
-    camera: Query<(&Camera, &GlobalTransform)>
+    camera: Query<(&Camera, &GlobalTransform)>,
+    grid: Option<Res<Walkability>>,
+    mut clicked: Local<Option<IVec2>>,
 ) {
     let (camera, camera_transform) = camera.single();
-    let (mut speed, mut target) = query.single_mut();
+    let (entity, mut target) = query.single_mut();
 
-    if keys.pressed(KeyCode::ShiftLeft) {
-        speed.fixed = Some(speed.running as f32);
-    } else {
-        speed.fixed = Some(speed.walking as f32);
-    }
-    
-    if buttons.pressed(MouseButton::Left) {
-        (*target).0 = windows
-            .single()
-            .cursor_position()
-            .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor).ok())
-            .map(|ray| ray.origin)
-            .map(|v| Vec3::new(v.x, v.y + 180.0, v.z));
+    if !buttons.pressed(MouseButton::Left) {
+        return;
     }
-}
 
-fn character_movement(
-    time: Res<Time>, 
-    mut query: Query<(
-        &AccountId,
-        &mut Transform,
-        &mut Speed,
-        &mut Target,
-        &mut Direction
-    ),With<EntityType>>,
-    state: Res<ConnectionState>,
-) {
-    for (id, mut transform, speed, mut target, mut facing) in &mut query {
-
-        if let Some(point) = target.0 {
-            if let Some(speed_value) = speed.fixed {
-                let cpos = transform.translation;
-                let tpos = Vec3::new(point.x, point.y, cpos.z);
-                let direction = (tpos - cpos).normalize();
-                let distance = cpos.distance(tpos);
-     
-                if distance > 10. {
-                    let amount = 1000. * time.delta_secs() * (speed_value / 10.0);
-                    let npos = cpos + direction * amount;
-                    transform.translation = npos;
-                    if id.0 == state.id {
-                        broadcast(Message::Move(
-                            state.id, 
-                            speed_value, 
-                            tpos,
-                            cpos
-                        ));
-                    }
-                } else {
-                    (*target).0 = None;
-                }
-    
-                // update the facing direction of the character
-                *facing = Direction::from(&direction);
-            }
-        }
+    let Some(point) = windows
+        .single()
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor).ok())
+        .map(|ray| ray.origin)
+        .map(|v| Vec3::new(v.x, v.y + 180.0, v.z)) else {
+        return;
+    };
+
+    // without a map there is no route to plan, so any move counts
+    let tile = grid.map(|grid| grid.world_to_tile(point));
+    if !buttons.just_pressed(MouseButton::Left) && tile.is_some() && tile == *clicked {
+        return;
     }
-}
\ No newline at end of file
+
+    *clicked = tile;
+
+    // walking somewhere else stops following anyone
+    commands.entity(entity).remove::<Follow>();
+    target.0 = Some(point);
+}
@@ -11,7 +11,17 @@ use bevy_simple_text_input::{
     TextInputValue
 };
 
-use crate::{plugins::button::{MyButton, MyButtonLabel}, queries, state::ConnectionState};
+use bevy::tasks::{block_on, poll_once};
+
+use crate::{
+    config::ClientConfig,
+    errors::{Error, Result},
+    plugins::button::{Disabled, MyButton, MyButtonLabel},
+    queries::{self, AccountInfo, AccountKey, Request},
+    session::SavedSession,
+    state::ConnectionState,
+    validation::{self, Invalid, RegisterErrors}
+};
 
 use super::{despawn_view, ViewState};
 
@@ -36,6 +46,35 @@ struct OnRegister;
 #[derive(Component)]
 struct OnError;
 
+#[derive(Component)]
+struct OnSpinner;
+
+#[derive(Component)]
+struct RememberToggle;
+
+// whether to save the session token after logging in
+#[derive(Clone, Copy, Default, Debug, Resource)]
+struct RememberMe(bool);
+
+// inline message displayed under a form input
+#[derive(Component)]
+struct FieldMessage(FormField);
+
+// the result of a login or registration request
+#[derive(Event)]
+enum QueryResponse {
+    Login(Result<AccountKey>),
+    Register(Result<AccountInfo>),
+    Resume(Result<AccountKey>),
+}
+
+// a login or registration request that is still running
+#[derive(Resource)]
+struct PendingQuery {
+    task: Request<QueryResponse>,
+    label: &'static str,
+}
+
 #[derive(Clone, Default, Eq, PartialEq, Debug, Hash, Resource)]
 struct RegisterInfo {
     username: String,
@@ -43,6 +82,10 @@ struct RegisterInfo {
     password2: String
 }
 
+// the latest validation result for `RegisterInfo`
+#[derive(Clone, Default, Debug, Resource)]
+struct RegisterValidation(RegisterErrors);
+
 #[derive(Clone, Default, Eq, PartialEq, Debug, Hash, Resource)]
 struct LoginInfo {
     username: String,
@@ -92,8 +135,15 @@ const NORMAL_BUTTON: Color = Color::srgb(1.0, 0.84, 0.0);
 const HOVERED_BUTTON: Color = Color::srgb(1.0, 0.92, 0.5);
 const PRESSED_BUTTON: Color = Color::srgb(1.0, 0.92, 0.5);
 
+const DISABLED_BUTTON: Color = Color::srgb(0.6, 0.55, 0.4);
+
+const SPINNER_FRAMES: [&str; 4] = ["|", "/", "-", "\\"];
+const SPINNER_FPS: f32 = 8.0;
+
 const BORDER_COLOR_ACTIVE: Color = Color::srgb(0.75, 0.75, 0.75);
 const BORDER_COLOR_INACTIVE: Color = Color::srgb(0.5, 0.2, 0.2);
+const BORDER_COLOR_ERROR: Color = Color::srgb(1.0, 0.84, 0.0);
+const FIELD_MESSAGE_COLOR: Color = Color::srgb(1.0, 0.84, 0.0);
 const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
 const INPUT_BACKGROUND_COLOR: Color = Color::srgb(0.598, 0.033, 0.033);
 
@@ -106,10 +156,20 @@ pub fn main_menu(app: &mut App) {
         .init_state::<MenuState>()
         .init_resource::<RegisterInfo>()
         .init_resource::<LoginInfo>()
+        .init_resource::<RegisterValidation>()
+        .init_resource::<RememberMe>()
+        .add_event::<QueryResponse>()
         
+        // try to skip the menu with a saved session
+        .add_systems(Startup, resume_session)
+
         // basic menu layout view
         .add_systems(OnEnter(ViewState::Menu), menu_setup)
-        .add_systems(OnExit(ViewState::Menu), despawn_view::<OnMenu>)
+        .add_systems(OnExit(ViewState::Menu), (
+            despawn_view::<OnMenu>,
+            cancel_query,
+            reset_menu_state
+        ))
 
         .add_systems(Update, tab_register_system
             .run_if(in_state(ViewState::Menu)))
@@ -127,12 +187,29 @@ pub fn main_menu(app: &mut App) {
         .add_systems(Update, (
             button_system,
             menu_action,
+            form_errors,
+            clear_form_errors,
             focus_system,
             handle_tab_key
         )
             .run_if(in_state(ViewState::Menu)))
 
-        .add_systems(Update, form_listener.run_if(in_state(ViewState::Menu)));
+        .add_systems(Update, (
+            poll_query,
+            escape_query,
+            spinner_system,
+            disable_buttons,
+            remember_system,
+            remember_label.run_if(resource_changed::<RememberMe>)
+        )
+            .run_if(in_state(ViewState::Menu)))
+
+        .add_systems(Update, form_listener.run_if(in_state(ViewState::Menu)))
+        .add_systems(Update, validate_register_form
+            .after(form_listener)
+            .after(clear_form_errors)
+            .run_if(in_state(ViewState::Menu))
+            .run_if(resource_changed::<RegisterInfo>));
 }
 
 fn handle_tab_key(
@@ -204,7 +281,14 @@ fn tab_login_system(
 }
 
 fn form_input(parent: &mut ChildBuilder<'_>, placeholder: &str, field: FormField, next: NextField) {
-    parent.spawn((
+    let wrapper = Node {
+        width: Val::Percent(100.0),
+        flex_direction: FlexDirection::Column,
+        row_gap: Val::Px(4.0),
+        ..default()
+    };
+
+    let input = (
         Node {
             width: Val::Percent(100.0),
             border: UiRect::all(Val::Px(2.0)),
@@ -228,7 +312,24 @@ fn form_input(parent: &mut ChildBuilder<'_>, placeholder: &str, field: FormField
         TextInputInactive(true),
         field,
         next
-    ));
+    );
+
+    let message = (
+        Text::new(""),
+        TextColor(FIELD_MESSAGE_COLOR),
+        TextFont {
+            font_size: 14.0,
+            ..default()
+        },
+        FieldMessage(field)
+    );
+
+    parent
+        .spawn(wrapper)
+        .with_children(|parent| {
+            parent.spawn(input);
+            parent.spawn(message);
+        });
 }
 
 fn form_button(parent: &mut ChildBuilder<'_>, label: &str, action: MenuButtonAction) {
@@ -240,9 +341,29 @@ fn form_button(parent: &mut ChildBuilder<'_>, label: &str, action: MenuButtonAct
 fn login_setup(
     mut commands: Commands, 
     query: Query<Entity, With<TabContainer>>,
+    remember: Res<RememberMe>,
 ) {
     if let Some(container) = query.iter().next() {
 
+        let remember_toggle = (
+            Button,
+            RememberToggle,
+            Node {
+                padding: UiRect::all(Val::Px(4.0)),
+                ..default()
+            },
+            BackgroundColor(TAB_BUTTON),
+        );
+
+        let remember_text = (
+            Text::new(remember_label_text(*remember)),
+            TextColor(WHITE.into()),
+            TextFont {
+                font_size: 16.0,
+                ..default()
+            }
+        );
+
         let tab_wrapper = (
             Node {
                 width: Val::Percent(100.0),
@@ -273,6 +394,10 @@ fn login_setup(
                         form_input(parent, "Username", FormField::LoginUsername, NextField::LoginPassword);
                         form_input(parent, "Password", FormField::LoginPassword, NextField::LoginUsername);
 
+                        parent
+                            .spawn(remember_toggle)
+                            .with_child(remember_text);
+
                         parent
                             .spawn(button_wrapper)
                             .with_children(|parent| {
@@ -423,6 +548,20 @@ fn menu_setup(
         OnError
     );
 
+    let spinner_text = (
+        Text::new(""),
+        TextColor(BLACK.into()),
+        TextFont {
+            font_size: 20.0,
+            ..default()
+        },
+        Node {
+            margin: UiRect::left(Val::Px(10.0)),
+            ..default()
+        },
+        OnSpinner
+    );
+
     let dialog_wrapper = Node {
         width: Val::Percent(100.0),
         height: Val::Percent(100.0),
@@ -516,7 +655,10 @@ fn menu_setup(
 
             parent
                 .spawn(error_wrapper)
-                .with_child(error_text);
+                .with_children(|parent| {
+                    parent.spawn(error_text);
+                    parent.spawn(spinner_text);
+                });
 
             parent
                 .spawn(dialog_wrapper)
@@ -557,10 +699,45 @@ fn form_listener(
     } 
 }
 
+fn validate_register_form(
+    register_info: Res<RegisterInfo>,
+    mut validation: ResMut<RegisterValidation>,
+    mut messages: Query<(&FieldMessage, &mut Text)>,
+) {
+    let previous = validation.0;
+    let current = validation::validate_register(
+        &register_info.username,
+        &register_info.password1,
+        &register_info.password2
+    );
+
+    // empty fields are invalid, but don't need a hint until touched
+    let hint = |value: &str, error: Option<Invalid>| match error {
+        Some(error) if !value.is_empty() => error.to_string(),
+        _ => String::new()
+    };
+
+    for (message, mut text) in &mut messages {
+        let (value, before, after) = match message.0 {
+            FormField::RegisterUsername => (&register_info.username, previous.username, current.username),
+            FormField::RegisterPassword1 => (&register_info.password1, previous.password1, current.password1),
+            FormField::RegisterPassword2 => (&register_info.password2, previous.password2, current.password2),
+            _ => continue
+        };
+
+        // leave server messages alone unless this field's result changed
+        if before != after || after.is_some() {
+            text.0 = hint(value, after);
+        }
+    }
+
+    validation.0 = current;
+}
+
 fn button_system(
     mut interaction_query: Query<
         (&Interaction, &mut BackgroundColor),
-        (Changed<Interaction>, With<Button>, Without<TabLoginButton>, Without<TabRegisterButton>),
+        (Changed<Interaction>, With<Button>, Without<TabLoginButton>, Without<TabRegisterButton>, Without<RememberToggle>, Without<Disabled>),
     >,
 ) {
     for (interaction, mut background_color) in &mut interaction_query {
@@ -591,63 +768,362 @@ fn focus_system(
     }
 }
 
+impl FormField {
+    // the input responsible for an error, if there is one
+    fn from_error(error: &Error, registering: bool) -> Option<Self> {
+        match (error, registering) {
+            (Error::UsernameTaken, _) => Some(Self::RegisterUsername),
+            (Error::PasswordMismatch, _) => Some(Self::RegisterPassword2),
+            (Error::BadCredentials, false) => Some(Self::LoginPassword),
+            (Error::InvalidField { field, .. }, true) => match field.as_str() {
+                "username" => Some(Self::RegisterUsername),
+                "password" | "password1" => Some(Self::RegisterPassword1),
+                "password2" => Some(Self::RegisterPassword2),
+                _ => None
+            },
+            (Error::InvalidField { field, .. }, false) => match field.as_str() {
+                "username" => Some(Self::LoginUsername),
+                "password" => Some(Self::LoginPassword),
+                _ => None
+            },
+            _ => None
+        }
+    }
+}
+
+fn form_errors(
+    mut responses: EventReader<QueryResponse>,
+    mut inputs: Query<(&FormField, &mut BorderColor), With<TextInput>>,
+    mut messages: Query<(&FieldMessage, &mut Text)>,
+) {
+    for response in responses.read() {
+        let failed = match response {
+            QueryResponse::Register(Err(error)) => FormField::from_error(error, true).map(|f| (f, error)),
+            QueryResponse::Login(Err(error)) => FormField::from_error(error, false).map(|f| (f, error)),
+            _ => None
+        };
+
+        if let Some((field, error)) = failed {
+            for (current, mut border) in &mut inputs {
+                if *current == field {
+                    border.0 = BORDER_COLOR_ERROR;
+                }
+            }
+            for (message, mut text) in &mut messages {
+                if message.0 == field {
+                    text.0 = error.to_string();
+                }
+            }
+        }
+    }
+}
+
+fn clear_form_errors(
+    mut inputs: Query<(&FormField, &TextInputInactive, &mut BorderColor), Changed<TextInputValue>>,
+    mut messages: Query<(&FieldMessage, &mut Text)>,
+) {
+    for (field, inactive, mut border) in &mut inputs {
+        if border.0 == BORDER_COLOR_ERROR {
+            border.0 = if inactive.0 { BORDER_COLOR_INACTIVE } else { BORDER_COLOR_ACTIVE };
+            for (message, mut text) in &mut messages {
+                if message.0 == *field {
+                    text.0 = "".into();
+                }
+            }
+        }
+    }
+}
+
+fn remember_label_text(remember: RememberMe) -> &'static str {
+    if remember.0 {
+        "[x] Remember me"
+    } else {
+        "[ ] Remember me"
+    }
+}
+
+fn remember_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>, With<RememberToggle>),
+    >,
+    mut remember: ResMut<RememberMe>,
+) {
+    for (interaction, mut background_color) in &mut interaction_query {
+        *background_color = match *interaction {
+            Interaction::Pressed => {
+                remember.0 = !remember.0;
+                TAB_BUTTON_HOVER.into()
+            },
+            Interaction::Hovered => TAB_BUTTON_HOVER.into(),
+            Interaction::None => TAB_BUTTON.into()
+        }
+    }
+}
+
+fn remember_label(
+    remember: Res<RememberMe>,
+    toggles: Query<&Children, With<RememberToggle>>,
+    mut labels: Query<&mut Text>,
+) {
+    for children in &toggles {
+        for child in children.iter() {
+            if let Ok(mut text) = labels.get_mut(*child) {
+                text.0 = remember_label_text(*remember).into();
+            }
+        }
+    }
+}
+
+fn resume_session(
+    mut commands: Commands,
+    mut remember: ResMut<RememberMe>,
+    config: Res<ClientConfig>,
+) {
+    let saved = match SavedSession::load() {
+        Ok(Some(saved)) => saved,
+        Ok(None) => return,
+        Err(e) => {
+            warn!("Could not load saved session: {}", e);
+            return;
+        }
+    };
+
+    // a saved session means the user opted in last time
+    remember.0 = true;
+
+    let config = config.clone();
+    let task = queries::spawn(async move {
+        QueryResponse::Resume(queries::validate(&config, &saved.token)
+            .await
+            .map(|info| AccountKey {
+                id: info.id,
+                name: info.username,
+                token: saved.token,
+            }))
+    });
+
+    commands.insert_resource(PendingQuery { task, label: "Resuming session" });
+}
+
+fn poll_query(
+    mut commands: Commands,
+    pending: Option<ResMut<PendingQuery>>,
+    mut responses: EventWriter<QueryResponse>,
+) {
+    if let Some(mut pending) = pending {
+        if let Some(response) = block_on(poll_once(&mut pending.task)) {
+            responses.send(response);
+            commands.remove_resource::<PendingQuery>();
+        }
+    }
+}
+
+// the tabs are rebuilt when the menu is next entered
+fn reset_menu_state(mut menu_state: ResMut<NextState<MenuState>>) {
+    menu_state.set(MenuState::None);
+}
+
+// dropping the request aborts it, so no response arrives
+fn cancel_query(mut commands: Commands) {
+    commands.remove_resource::<PendingQuery>();
+}
+
+fn escape_query(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    pending: Option<Res<PendingQuery>>,
+    mut error_query: Query<&mut Text, With<OnError>>,
+) {
+    if pending.is_some() && keys.just_pressed(KeyCode::Escape) {
+        commands.remove_resource::<PendingQuery>();
+        for mut error_message in &mut error_query {
+            error_message.0 = "Request cancelled".into();
+        }
+    }
+}
+
+fn spinner_system(
+    time: Res<Time>,
+    pending: Option<Res<PendingQuery>>,
+    mut query: Query<&mut Text, With<OnSpinner>>,
+) {
+    let value = match pending {
+        Some(pending) => {
+            let frame = (time.elapsed_secs() * SPINNER_FPS) as usize % SPINNER_FRAMES.len();
+            format!("{} {} (Esc to cancel)", pending.label, SPINNER_FRAMES[frame])
+        },
+        None => String::new()
+    };
+
+    for mut text in &mut query {
+        if text.0 != value {
+            text.0 = value.clone();
+        }
+    }
+}
+
+fn disable_buttons(
+    mut commands: Commands,
+    pending: Option<Res<PendingQuery>>,
+    validation: Res<RegisterValidation>,
+    mut query: Query<(Entity, &MenuButtonAction, &mut BackgroundColor, Has<Disabled>), With<Button>>,
+) {
+    for (entity, action, mut background_color, disabled) in &mut query {
+        let disable = match action {
+            MenuButtonAction::Login => pending.is_some(),
+            MenuButtonAction::Register => pending.is_some() || !validation.0.is_valid(),
+            _ => false
+        };
+
+        if disable && !disabled {
+            commands.entity(entity).insert(Disabled);
+            *background_color = DISABLED_BUTTON.into();
+        }
+        else if !disable && disabled {
+            commands.entity(entity).remove::<Disabled>();
+            *background_color = NORMAL_BUTTON.into();
+        }
+    }
+}
+
 fn menu_action(
+    mut commands: Commands,
     mut view_state: ResMut<NextState<ViewState>>,
     mut menu_state: ResMut<NextState<MenuState>>,
     interaction_query: Query<
         (&Interaction, &MenuButtonAction),
-        (Changed<Interaction>, With<Button>),
+        (Changed<Interaction>, With<Button>, Without<Disabled>),
     >,
-    mut error_query: Query<&mut Text, With<OnError>>,
+    mut error_query: Query<&mut Text, (With<OnError>, Without<FieldMessage>)>,
     mut connection_state: ResMut<ConnectionState>,
+    mut responses: EventReader<QueryResponse>,
+    pending: Option<Res<PendingQuery>>,
+    remember: Res<RememberMe>,
+    config: Res<ClientConfig>,
     mut app_exit_events: EventWriter<AppExit>,
     register_info: Res<RegisterInfo>,
     login_info: Res<LoginInfo>,
 ) {
+    // the message to show, looked up once there is something to report
+    let mut report: Option<String> = None;
+
+    for response in responses.read() {
+        match response {
+            QueryResponse::Register(info) => {
+                match info {
+                    Ok(_) => {
+                        report = Some("".into());
+                        menu_state.set(MenuState::Login);
+                    },
+                    // field errors are displayed inline by `form_errors`
+                    Err(error) if FormField::from_error(error, true).is_some() => {
+                        report = Some("".into());
+                    },
+                    Err(error) => {
+                        report = Some(error.to_string());
+                    }
+                }
+            },
+            QueryResponse::Login(info) => {
+                match info {
+                    Ok(data) => {
+                        let saved = if remember.0 {
+                            SavedSession {
+                                id: data.id,
+                                username: data.name.clone(),
+                                token: data.token.clone()
+                            }.save()
+                        } else {
+                            SavedSession::delete()
+                        };
+
+                        if let Err(e) = saved {
+                            warn!("Could not update saved session: {}", e);
+                        }
+
+                        report = Some("".into());
+                        connection_state.id = data.id;
+                        connection_state.username = data.name.clone();
+                        connection_state.token = Some(data.token.clone());
+                        view_state.set(ViewState::Game)
+                    },
+                    Err(error) if FormField::from_error(error, false).is_some() => {
+                        report = Some("".into());
+                    },
+                    Err(error) => {
+                        report = Some(error.to_string());
+                    }
+                }
+            },
+            QueryResponse::Resume(info) => {
+                match info {
+                    Ok(data) => {
+                        connection_state.id = data.id;
+                        connection_state.username = data.name.clone();
+                        connection_state.token = Some(data.token.clone());
+                        view_state.set(ViewState::Game)
+                    },
+                    // the server rejected the token, so it won't work later either
+                    Err(Error::BadCredentials) => {
+                        if let Err(e) = SavedSession::delete() {
+                            warn!("Could not delete saved session: {}", e);
+                        }
+                        report = Some("Session expired, please log in".into());
+                    },
+                    // other failures may pass, so the session is kept to try again
+                    Err(error) => {
+                        report = Some(error.to_string());
+                    }
+                }
+            }
+        }
+    }
+
     for (interaction, menu_button_action) in &interaction_query {
-        let mut error_message = error_query.single_mut();
         if *interaction == Interaction::Pressed {
             match menu_button_action {
                 MenuButtonAction::Quit => {
                     app_exit_events.send(AppExit::Success);
                 },
-                MenuButtonAction::Register => {
-
-                    let info = queries::register(
-                        register_info.username.clone(), 
-                        register_info.password1.clone(), 
-                        register_info.password2.clone()
-                    );
-
-                    if info.is_ok() {
-                        menu_state.set(MenuState::Login);
-                    } else {
-                        // display failure in UI
-                        dbg!(info);
-                        error_message.0 = "Registration failed".into();
-                    }
+                MenuButtonAction::Register if pending.is_none() => {
+                    let config = config.clone();
+                    let info = register_info.clone();
+
+                    let task = queries::spawn(async move {
+                        QueryResponse::Register(queries::register(
+                            &config,
+                            info.username, 
+                            info.password1, 
+                            info.password2
+                        ).await)
+                    });
 
+                    report = Some("".into());
+                    commands.insert_resource(PendingQuery { task, label: "Registering" });
                 },
-                MenuButtonAction::Login => {
-
-                    let info = queries::login(
-                        login_info.username.clone(), 
-                        login_info.password.clone(), 
-                    );
+                MenuButtonAction::Login if pending.is_none() => {
+                    let config = config.clone();
+                    let info = login_info.clone();
+
+                    let task = queries::spawn(async move {
+                        QueryResponse::Login(queries::login(
+                            &config,
+                            info.username, 
+                            info.password, 
+                        ).await)
+                    });
 
-                    if let Ok(data) = info {
-                        connection_state.id = data.id;
-                        connection_state.username = data.name;
-                        connection_state.token = Some(data.token.clone());
-                        view_state.set(ViewState::Game)
-                    } else {
-                        // display failure in UI
-                        error_message.0 = "Login failed".into();
-                    }
-                    
+                    report = Some("".into());
+                    commands.insert_resource(PendingQuery { task, label: "Logging in" });
                 },
                 _ => ()
             }
         }
     }
-}
\ No newline at end of file
+    if let Some(message) = report {
+        if let Ok(mut text) = error_query.get_single_mut() {
+            text.0 = message;
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::time::{Duration, Instant};
+use bevy::asset::LoadState;
+use bevy::prelude::*;
+
+use tinker::player::{Animation, Direction, DEFAULT_ANIMATIONS};
+use tinker::plugins::animation::{AnimationFile, AnimationSet, AnimationSetError, AnimationSetPlugin};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+// a 2x2 sheet with the walking clip swapped for `walking`
+fn set(walking: &str) -> String {
+    format!("(
+        sheet: \"sprites/character2.png\",
+        cell: (255, 512),
+        columns: 2,
+        rows: 2,
+        idle: (fps: 5.0, frames: (right: [0])),
+        walking: {},
+        running: (fps: 10.0, looping: false, frames: (right: [2, 3])),
+    )", walking)
+}
+
+fn app() -> App {
+    let mut app = App::new();
+    app
+        .add_plugins((MinimalPlugins, AssetPlugin::default(), ImagePlugin::default()))
+        .init_asset::<TextureAtlasLayout>()
+        .add_plugins(AnimationSetPlugin);
+    app
+}
+
+#[test]
+fn default_animations_load() {
+    let mut app = app();
+
+    let handle: Handle<AnimationSet> = app
+        .world()
+        .resource::<AssetServer>()
+        .load(DEFAULT_ANIMATIONS);
+
+    let start = Instant::now();
+    loop {
+        app.update();
+
+        let server = app.world().resource::<AssetServer>();
+        match server.load_state(&handle) {
+            LoadState::Loaded => break,
+            LoadState::Failed(error) => panic!("{}", error),
+            _ => (),
+        }
+
+        assert!(start.elapsed() < TIMEOUT, "timed out loading animations");
+        std::thread::sleep(Duration::from_millis(5));
+    }
+
+    let sets = app.world().resource::<Assets<AnimationSet>>();
+    let set = sets.get(&handle).unwrap();
+
+    assert!(set.idle.looping);
+    assert_eq!(set.idle.frames.first(Direction::BotRight), Some(1));
+    assert_eq!(set.walking.frames.next(Direction::TopLeft, 8, true), 14);
+    assert_eq!(set.walking.frames.next(Direction::TopLeft, 14, true), 8);
+    assert_eq!(set.running.frames.next(Direction::TopLeft, 14, false), 14);
+
+    let layouts = app.world().resource::<Assets<TextureAtlasLayout>>();
+    assert_eq!(layouts.get(&set.layout).unwrap().textures.len(), 18);
+}
+
+#[test]
+fn eight_directions() {
+    assert_eq!(Direction::from(&Vec3::new(1., 0., 0.)), Direction::Right);
+    assert_eq!(Direction::from(&Vec3::new(0., 1., 0.)), Direction::Top);
+    assert_eq!(Direction::from(&Vec3::new(-1., 0., 0.)), Direction::Left);
+    assert_eq!(Direction::from(&Vec3::new(0., -1., 0.)), Direction::Bot);
+    assert_eq!(Direction::from(&Vec3::new(-1., -1., 0.)), Direction::BotLeft);
+
+    // along an isometric axis, not quite 45 degrees
+    assert_eq!(Direction::from(&Vec3::new(2., 1., 0.)), Direction::TopRight);
+    assert_eq!(Direction::from(&Vec3::new(2., -1., 0.)), Direction::BotRight);
+
+    // just either side of the boundary between two directions
+    assert_eq!(Direction::from(&Vec3::new(1., 0.41, 0.)), Direction::Right);
+    assert_eq!(Direction::from(&Vec3::new(1., 0.42, 0.)), Direction::TopRight);
+}
+
+#[test]
+fn missing_directions_fall_back() {
+    let animation: Animation = ron::from_str("(
+        right: [1, 2],
+        topright: [3],
+        top: [4],
+    )").unwrap();
+
+    // drawn as they are
+    assert_eq!(animation.facing(Direction::Right), (&vec![1, 2], false));
+
+    // mirrored from the other side
+    assert_eq!(animation.facing(Direction::Left), (&vec![1, 2], true));
+    assert_eq!(animation.facing(Direction::TopLeft), (&vec![3], true));
+
+    // borrowed from the nearest direction with frames
+    assert_eq!(animation.facing(Direction::BotRight), (&vec![1, 2], false));
+    assert_eq!(animation.facing(Direction::BotLeft), (&vec![1, 2], true));
+    assert!(animation.facing(Direction::Bot).0.is_empty());
+}
+
+#[test]
+fn unknown_directions_are_rejected() {
+    assert!(ron::from_str::<Animation>("(rihgt: [1])").is_err());
+    assert!(matches!(
+        AnimationFile::parse(set("(fps: 8.0, frames: (rihgt: [1]))").as_bytes()),
+        Err(AnimationSetError::Parse(_))));
+}
+
+#[test]
+fn clips_need_a_direction() {
+    assert!(ron::from_str::<Animation>("()").unwrap().is_empty());
+    assert!(matches!(
+        AnimationFile::parse(set("(fps: 8.0, frames: (top: [], bot: []))").as_bytes()),
+        Err(AnimationSetError::Invalid(_))));
+}
+
+#[test]
+fn valid_set_parses() {
+    let file = AnimationFile::parse(set("(fps: 8.0, frames: (right: [0, 1, 2, 3]))").as_bytes()).unwrap();
+    assert_eq!(file.walking.frames.first(Direction::Left), Some(0));
+    assert!(!file.running.looping);
+}
+
+#[test]
+fn invalid_sets_are_rejected() {
+    let invalid = [
+        // past the end of the sheet
+        "(fps: 8.0, frames: (right: [1, 4]))",
+        // nothing to show
+        "(fps: 8.0, frames: ())",
+        // never advances
+        "(fps: 0.0, frames: (right: [1]))",
+        "(fps: -2.0, frames: (right: [1]))",
+    ];
+
+    for clip in invalid {
+        assert!(
+            matches!(AnimationFile::parse(set(clip).as_bytes()), Err(AnimationSetError::Invalid(_))),
+            "{} was accepted", clip);
+    }
+}
@@ -0,0 +1,56 @@
+use bevy::math::Vec3;
+use tinker_records::messages::Message;
+
+use tinker::chat::{self, Channel, ChatMessage, MAX_LENGTH};
+use tinker::plugins::network::Packet;
+
+#[test]
+fn parse_channels() {
+    let current = Channel::Local;
+
+    assert_eq!(chat::parse("hello", &current), Some((Channel::Local, "hello".into())));
+    assert_eq!(chat::parse("/g hello all", &current), Some((Channel::Global, "hello all".into())));
+    assert_eq!(chat::parse("/l  hi ", &Channel::Global), Some((Channel::Local, "hi".into())));
+    assert_eq!(
+        chat::parse("/w bob see you", &current),
+        Some((Channel::Whisper("bob".into()), "see you".into())));
+}
+
+#[test]
+fn parse_nothing_to_send() {
+    let current = Channel::Global;
+
+    assert_eq!(chat::parse("", &current), None);
+    assert_eq!(chat::parse("   ", &current), None);
+    assert_eq!(chat::parse("/g", &current), None);
+    assert_eq!(chat::parse("/w bob", &current), None);
+}
+
+#[test]
+fn long_messages_are_cut() {
+    let text = "a".repeat(MAX_LENGTH * 2);
+    let message = ChatMessage::new(1, "alice", Channel::Global, &text);
+    assert_eq!(message.text.len(), MAX_LENGTH);
+}
+
+#[test]
+fn records_are_sent_unchanged() {
+    let message = Message::Move(3, 2.0, Vec3::new(1., 2., 0.), Vec3::ZERO);
+
+    let plain = serde_json::to_string(&message).unwrap();
+    let packet = serde_json::to_string(&Packet::Record(message)).unwrap();
+    assert_eq!(plain, packet);
+
+    assert!(matches!(serde_json::from_str::<Packet>(&plain), Ok(Packet::Record(_))));
+}
+
+#[test]
+fn chat_round_trip() {
+    let message = ChatMessage::new(1, "alice", Channel::Whisper("bob".into()), "hi");
+    let text = serde_json::to_string(&Packet::Chat { chat: message.clone() }).unwrap();
+
+    match serde_json::from_str::<Packet>(&text) {
+        Ok(Packet::Chat { chat }) => assert_eq!(chat, message),
+        other => panic!("unexpected {:?}", other),
+    }
+}
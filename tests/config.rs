@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use tinker::config::ClientConfig;
+use tinker::errors::Error;
+
+fn args(values: &[&str]) -> Vec<String> {
+    values.iter().map(|v| v.to_string()).collect()
+}
+
+fn vars(values: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+    let values: HashMap<String, String> = values
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    move |name| values.get(name).cloned()
+}
+
+// a config file unique to the calling test
+fn file(name: &str, text: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("tinker-{}-{}.toml", name, std::process::id()));
+    std::fs::write(&path, text).unwrap();
+    path
+}
+
+#[test]
+fn defaults() {
+    let config = ClientConfig::load_from(&args(&[]), vars(&[])).unwrap();
+
+    assert_eq!(config.http_url("/login"), "http://localhost:8080/login");
+    assert_eq!(config.ws_url("/connect/a"), "ws://localhost:8080/connect/a");
+}
+
+#[test]
+fn file_then_environment_then_arguments() {
+    let path = file("precedence", "host = \"file\"\nport = 1000\nprefix = \"api\"\ntimeout = 3\n");
+    let config = path.to_str().unwrap();
+
+    let from_file = ClientConfig::load_from(&args(&["--config", config]), vars(&[])).unwrap();
+    assert_eq!(from_file.host, "file");
+    assert_eq!(from_file.port, 1000);
+    assert_eq!(from_file.timeout, 3);
+    assert_eq!(from_file.http_url("/login"), "http://file:1000/api/login");
+
+    let from_env = ClientConfig::load_from(
+        &args(&["--config", config]),
+        vars(&[("TINKER_HOST", "env"), ("TINKER_PORT", "2000")])
+    ).unwrap();
+    assert_eq!(from_env.host, "env");
+    assert_eq!(from_env.port, 2000);
+    assert_eq!(from_env.timeout, 3);
+
+    let from_args = ClientConfig::load_from(
+        &args(&["--config", config, "--host", "cli", "--timeout=7"]),
+        vars(&[("TINKER_HOST", "env"), ("TINKER_PORT", "2000")])
+    ).unwrap();
+    assert_eq!(from_args.host, "cli");
+    assert_eq!(from_args.port, 2000);
+    assert_eq!(from_args.timeout, 7);
+
+    std::fs::remove_file(path).ok();
+}
+
+#[test]
+fn config_file_from_environment() {
+    let path = file("environment", "port = 3000\n");
+
+    let config = ClientConfig::load_from(
+        &args(&[]),
+        vars(&[("TINKER_CONFIG", path.to_str().unwrap())])
+    ).unwrap();
+    assert_eq!(config.port, 3000);
+
+    std::fs::remove_file(path).ok();
+}
+
+#[test]
+fn last_tls_flag_wins() {
+    let on = ClientConfig::load_from(&args(&["--no-tls", "--tls"]), vars(&[])).unwrap();
+    assert_eq!(on.http_url("/"), "https://localhost:8080/");
+    assert_eq!(on.ws_url("/"), "wss://localhost:8080/");
+
+    let off = ClientConfig::load_from(&args(&["--tls", "--no-tls"]), vars(&[("TINKER_TLS", "true")])).unwrap();
+    assert_eq!(off.http_url("/"), "http://localhost:8080/");
+
+    let env = ClientConfig::load_from(&args(&[]), vars(&[("TINKER_TLS", "yes")])).unwrap();
+    assert!(env.tls());
+}
+
+#[test]
+fn scheme() {
+    let path = file("scheme", "scheme = \"HTTPS\"\n");
+    let config = path.to_str().unwrap();
+
+    let from_file = ClientConfig::load_from(&args(&["--config", config]), vars(&[])).unwrap();
+    assert_eq!(from_file.http_url("/login"), "https://localhost:8080/login");
+    assert_eq!(from_file.ws_url("/connect/a"), "wss://localhost:8080/connect/a");
+
+    let from_env = ClientConfig::load_from(
+        &args(&["--config", config]),
+        vars(&[("TINKER_SCHEME", "http")])
+    ).unwrap();
+    assert_eq!(from_env.ws_url("/"), "ws://localhost:8080/");
+
+    // turning TLS on or off overrides the scheme
+    let from_args = ClientConfig::load_from(&args(&["--scheme", "http", "--tls"]), vars(&[])).unwrap();
+    assert_eq!(from_args.http_url("/"), "https://localhost:8080/");
+
+    let from_vars = ClientConfig::load_from(
+        &args(&[]),
+        vars(&[("TINKER_SCHEME", "https"), ("TINKER_TLS", "off")])
+    ).unwrap();
+    assert_eq!(from_vars.http_url("/"), "http://localhost:8080/");
+
+    std::fs::remove_file(path).ok();
+}
+
+#[test]
+fn invalid_values() {
+    let invalid = |a: &[&str], v: &[(&str, &str)]| {
+        matches!(ClientConfig::load_from(&args(a), vars(v)), Err(Error::InvalidConfig(_)))
+    };
+
+    assert!(invalid(&["--host", " "], &[]));
+    assert!(invalid(&["--host", "http://example.com"], &[]));
+    assert!(invalid(&["--port", "0"], &[]));
+    assert!(invalid(&["--port", "eighty"], &[]));
+    assert!(invalid(&["--timeout", "0"], &[]));
+    assert!(invalid(&["--host"], &[]));
+    assert!(invalid(&[], &[("TINKER_TLS", "maybe")]));
+    assert!(invalid(&["--scheme", "ftp"], &[]));
+    assert!(invalid(&[], &[("TINKER_SCHEME", "wss")]));
+    assert!(invalid(&[], &[("TINKER_PORT", "70000")]));
+}
+
+#[test]
+fn prefix_is_normalised() {
+    let config = ClientConfig::load_from(&args(&["--prefix", "game/"]), vars(&[])).unwrap();
+    assert_eq!(config.http_url("/login"), "http://localhost:8080/game/login");
+}
+
+#[test]
+fn missing_config_file() {
+    let result = ClientConfig::load_from(&args(&["--config", "/nonexistent/tinker.toml"]), vars(&[]));
+    assert!(matches!(result, Err(Error::ConfigReadError(_))));
+}
@@ -0,0 +1,89 @@
+use bevy::math::Vec3;
+
+use tinker::plugins::interpolation::{InterpolationSettings, Snapshot, SnapshotBuffer};
+
+const EPSILON: f32 = 1e-4;
+
+fn x(value: f32) -> Vec3 {
+    Vec3::new(value, 0., 0.)
+}
+
+// a character moving one unit along x every 100ms, which is the default delay
+fn walking(target: Vec3) -> SnapshotBuffer {
+    let mut buffer = SnapshotBuffer::starting_at(0.0, x(0.));
+    buffer.push(Snapshot { time: 0.1, position: x(1.), target, speed: 1. });
+    buffer.push(Snapshot { time: 0.2, position: x(2.), target, speed: 1. });
+    buffer
+}
+
+#[test]
+fn empty_buffer() {
+    let mut buffer = SnapshotBuffer::default();
+    assert!(buffer.sample(1.0, &InterpolationSettings::default()).is_none());
+}
+
+#[test]
+fn single_snapshot() {
+    let mut buffer = SnapshotBuffer::starting_at(1.0, x(5.));
+    let settings = InterpolationSettings::default();
+
+    // before, at and after the snapshot the character stands still
+    assert_eq!(buffer.sample(0.5, &settings), Some((x(5.), Vec3::ZERO)));
+    assert_eq!(buffer.sample(1.0, &settings), Some((x(5.), Vec3::ZERO)));
+    assert_eq!(buffer.sample(9.0, &settings), Some((x(5.), Vec3::ZERO)));
+}
+
+#[test]
+fn interpolates_between_snapshots() {
+    let mut buffer = walking(x(10.));
+    let settings = InterpolationSettings::default();
+
+    let (position, velocity) = buffer.sample(0.05, &settings).unwrap();
+    assert!(position.abs_diff_eq(x(0.5), EPSILON));
+    assert!(velocity.abs_diff_eq(x(10.), EPSILON));
+
+    let (position, _) = buffer.sample(0.15, &settings).unwrap();
+    assert!(position.abs_diff_eq(x(1.5), EPSILON));
+}
+
+#[test]
+fn starting_to_move_crosses_the_gap_in_the_delay() {
+    let mut buffer = SnapshotBuffer::starting_at(0.0, x(0.));
+    buffer.push(Snapshot { time: 1.0, position: x(1.), target: x(10.), speed: 1. });
+    let settings = InterpolationSettings::default();
+
+    // still standing until `delay` before the move arrived
+    assert_eq!(buffer.sample(0.5, &settings), Some((x(0.), Vec3::ZERO)));
+
+    let (position, velocity) = buffer.sample(0.95, &settings).unwrap();
+    assert!(position.abs_diff_eq(x(0.5), EPSILON));
+    assert!(velocity.abs_diff_eq(x(10.), EPSILON));
+}
+
+#[test]
+fn extrapolation_is_clamped() {
+    let mut buffer = walking(x(10.));
+    let settings = InterpolationSettings::default();
+
+    // late packets carry the character on at the same velocity
+    let (position, velocity) = buffer.sample(0.3, &settings).unwrap();
+    assert!(position.abs_diff_eq(x(3.), EPSILON));
+    assert!(velocity.abs_diff_eq(x(10.), EPSILON));
+
+    // but no further than `max_extrapolation` past the last snapshot
+    let (position, velocity) = buffer.sample(5.0, &settings).unwrap();
+    assert!(position.abs_diff_eq(x(4.5), EPSILON));
+    assert_eq!(velocity, Vec3::ZERO);
+
+    // and it rests there afterwards
+    let (position, _) = buffer.sample(6.0, &settings).unwrap();
+    assert!(position.abs_diff_eq(x(4.5), EPSILON));
+}
+
+#[test]
+fn extrapolation_stops_at_the_target() {
+    let mut buffer = walking(x(2.5));
+    let settings = InterpolationSettings::default();
+
+    assert_eq!(buffer.sample(0.3, &settings), Some((x(2.5), Vec3::ZERO)));
+}
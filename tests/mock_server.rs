@@ -0,0 +1,321 @@
+use std::future::Future;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use tinker_records::messages::{Message, Value};
+
+use tinker::chat::{Channel, ChatMessage};
+use tinker::errors::Error;
+use tinker::mock::{self, MockServer, Step};
+use tinker::plugins::network::{Backpressure, Incoming, IncomingChat, IncomingStats, NetworkPlugin, Outgoing, OutgoingChat};
+use tinker::plugins::shutdown::ShutdownPlugin;
+use tinker::queries;
+use tinker::state::ConnectionState;
+use tinker::stats::{StatChange, StatMessage};
+use tinker::views::ViewState;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+// the queries need a tokio runtime to run on
+fn wait<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+#[derive(Resource, Default)]
+struct Received(Vec<Message>);
+
+fn collect(mut events: EventReader<Incoming>, mut received: ResMut<Received>) {
+    for Incoming(message) in events.read() {
+        received.0.push(message.clone());
+    }
+}
+
+#[derive(Resource, Default)]
+struct ReceivedStats(Vec<StatMessage>);
+
+fn collect_stats(mut events: EventReader<IncomingStats>, mut received: ResMut<ReceivedStats>) {
+    for IncomingStats(message) in events.read() {
+        received.0.push(message.clone());
+    }
+}
+
+#[derive(Resource, Default)]
+struct ReceivedChat(Vec<ChatMessage>);
+
+fn collect_chat(mut events: EventReader<IncomingChat>, mut received: ResMut<ReceivedChat>) {
+    for IncomingChat(message) in events.read() {
+        received.0.push(message.clone());
+    }
+}
+
+// logs in and builds an app that connects on its next update, keeping
+// everything it receives
+fn connect(server: &MockServer, username: &str) -> App {
+    let key = wait(queries::login(&server.config(), username, "password1")).unwrap();
+
+    let mut app = App::new();
+    app
+        .add_plugins((MinimalPlugins, StatesPlugin))
+        .insert_resource(server.config())
+        .insert_resource(ConnectionState {
+            id: key.id,
+            token: Some(key.token),
+            ..Default::default()
+        })
+        .init_state::<ViewState>()
+        .init_resource::<Received>()
+        .init_resource::<ReceivedStats>()
+        .init_resource::<ReceivedChat>()
+        .add_plugins((NetworkPlugin, ShutdownPlugin))
+        .add_systems(Update, (collect, collect_stats, collect_chat));
+
+    app.world_mut()
+        .resource_mut::<NextState<ViewState>>()
+        .set(ViewState::Game);
+    app
+}
+
+// updates the app until `done` is true or the timeout has passed
+fn run_until(app: &mut App, mut done: impl FnMut(&mut App) -> bool) {
+    let start = Instant::now();
+    while !done(app) && start.elapsed() < TIMEOUT {
+        app.update();
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
+
+fn received(app: &App) -> &Vec<Message> {
+    &app.world().resource::<Received>().0
+}
+
+#[test]
+fn register_login_and_validate() {
+    let server = MockServer::start("127.0.0.1:0").unwrap();
+    let config = server.config();
+
+    let account = wait(queries::register(&config, "alice", "password1", "password1")).unwrap();
+    assert_eq!(account.username, "alice");
+
+    assert!(matches!(
+        wait(queries::register(&config, "alice", "password1", "password1")),
+        Err(Error::UsernameTaken)));
+
+    assert!(matches!(
+        wait(queries::login(&config, "alice", "wrong")),
+        Err(Error::BadCredentials)));
+
+    let key = wait(queries::login(&config, "alice", "password1")).unwrap();
+    assert_eq!(key.id, account.id);
+
+    let info = wait(queries::validate(&config, &key.token)).unwrap();
+    assert_eq!(info.id, account.id);
+}
+
+#[test]
+fn oversized_request_is_rejected() {
+    let server = MockServer::start("127.0.0.1:0").unwrap();
+
+    // the body is never sent, so reading it would hang
+    let mut stream = TcpStream::connect(server.address()).unwrap();
+    stream.write_all(b"POST /register HTTP/1.1\r\nContent-Length: 1000000000\r\n\r\n").unwrap();
+
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).unwrap();
+    assert!(reply.starts_with("HTTP/1.1 413"));
+}
+
+#[test]
+fn scripted_session() {
+    let server = MockServer::start("127.0.0.1:0").unwrap();
+    let id = server.add_account("bob", "password1");
+
+    server.script(|_| vec![
+        Step::Wait(Duration::from_millis(50)),
+        Step::Send(mock::connect(99, "carol", 1.0, 2.0)),
+        Step::Send(mock::moving(99, 1.0, Vec3::new(5.0, 5.0, 0.0), Vec3::new(1.0, 2.0, 0.0))),
+        Step::Send(mock::disconnect(99)),
+    ]);
+
+    let mut app = connect(&server, "bob");
+    run_until(&mut app, |app| received(app).len() >= 4);
+
+    let received = received(&app);
+    assert_eq!(received.len(), 4);
+    assert!(matches!(received[0].value, Value::Initial(_)));
+    assert!(matches!(received[1].value, Value::Connect(_)));
+    assert!(matches!(received[2].value, Value::Move(_)));
+    assert!(matches!(received[3].value, Value::Disconnect(_)));
+
+    app.world_mut().send_event(Outgoing(mock::moving(id, 1.0, Vec3::ONE, Vec3::ZERO)));
+
+    let mut sent = Vec::new();
+    run_until(&mut app, |_| {
+        sent.extend(server.take_received());
+        !sent.is_empty()
+    });
+
+    assert_eq!(sent.len(), 1);
+    assert!(matches!(sent[0].value, Value::Move(_)));
+}
+
+#[test]
+fn scripted_stats() {
+    let server = MockServer::start("127.0.0.1:0").unwrap();
+    let id = server.add_account("dave", "password1");
+
+    server.script(|id| vec![
+        Step::Stats(mock::stats(id, StatChange::Health { current: 60, maximum: 120 })),
+        Step::Send(mock::moving(id, 1.0, Vec3::ONE, Vec3::ZERO)),
+        Step::Stats(mock::stats(id, StatChange::Level { level: 2, experience: 5, required: 200 })),
+    ]);
+
+    let mut app = connect(&server, "dave");
+    run_until(&mut app, |app| app.world().resource::<ReceivedStats>().0.len() >= 2);
+
+    // stats don't get in the way of ordinary messages
+    let received = received(&app);
+    assert_eq!(received.len(), 2);
+    assert!(matches!(received[0].value, Value::Initial(_)));
+    assert!(matches!(received[1].value, Value::Move(_)));
+
+    let stats = &app.world().resource::<ReceivedStats>().0;
+    assert_eq!(stats.len(), 2);
+    assert_eq!(stats[0], StatMessage::new(id, StatChange::Health { current: 60, maximum: 120 }));
+    assert_eq!(stats[1], StatMessage::new(id, StatChange::Level { level: 2, experience: 5, required: 200 }));
+}
+
+#[test]
+fn backed_up_messages_are_not_dropped() {
+    let server = MockServer::start("127.0.0.1:0").unwrap();
+    server.add_account("erin", "password1");
+
+    let mut app = connect(&server, "erin");
+
+    // moves for different accounts aren't coalesced, so all of these
+    // have to get through
+    let count = 2000;
+    for i in 0..count {
+        app.world_mut().send_event(Outgoing(mock::moving(i, 1.0, Vec3::ONE, Vec3::ZERO)));
+    }
+
+    let mut sent = Vec::new();
+    let mut backed_up = false;
+    run_until(&mut app, |app| {
+        backed_up |= app.world().resource::<Backpressure>().0;
+        sent.extend(server.take_received());
+        sent.len() >= count as usize
+    });
+
+    assert!(backed_up);
+    assert_eq!(sent.len(), count as usize);
+    assert!(sent.iter().enumerate().all(|(i, m)| m.header.account_id == i as i32));
+}
+
+#[test]
+fn leaving_sends_disconnect() {
+    let server = MockServer::start("127.0.0.1:0").unwrap();
+    let id = server.add_account("fred", "password1");
+
+    let mut app = connect(&server, "fred");
+    run_until(&mut app, |app| !received(app).is_empty());
+
+    // as the escape menu does, the view is left in the same frame
+    app.world_mut().send_event(Outgoing::disconnect(id));
+    app.world_mut()
+        .resource_mut::<NextState<ViewState>>()
+        .set(ViewState::Menu);
+
+    let mut sent = Vec::new();
+    run_until(&mut app, |_| {
+        sent.extend(server.take_received());
+        !sent.is_empty()
+    });
+
+    assert_eq!(sent.len(), 1);
+    assert!(matches!(sent[0].value, Value::Disconnect(_)));
+    assert_eq!(sent[0].header.account_id, id);
+}
+
+#[test]
+fn local_chat_stays_local() {
+    let server = MockServer::start("127.0.0.1:0").unwrap();
+    let near = server.add_account("gina", "password1");
+    let far = server.add_account("hank", "password1");
+
+    let mut apps = [connect(&server, "gina"), connect(&server, "hank")];
+
+    let run = |apps: &mut [App; 2], done: &dyn Fn(&[App; 2]) -> bool| {
+        let start = Instant::now();
+        while !done(apps) && start.elapsed() < TIMEOUT {
+            for app in apps.iter_mut() {
+                app.update();
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    };
+
+    run(&mut apps, &|_| server.connected() == 2);
+
+    // one player has walked well out of earshot, and is heading further
+    let position = Vec3::new(5000., 0., 0.);
+    apps[1].world_mut().send_event(Outgoing(mock::moving(far, 1.0, Vec3::new(5200., 0., 0.), position)));
+    run(&mut apps, &|_| server.take_received().iter().any(|m| matches!(m.value, Value::Move(_))));
+
+    // the global line follows the local one, so once it has arrived the
+    // local one would have too
+    apps[0].world_mut().send_event(OutgoingChat(ChatMessage::new(near, "gina", Channel::Local, "psst")));
+    apps[0].world_mut().send_event(OutgoingChat(ChatMessage::new(near, "gina", Channel::Global, "hello")));
+
+    let heard_global = |app: &App| app.world()
+        .resource::<ReceivedChat>().0
+        .iter()
+        .any(|m| m.channel == Channel::Global);
+
+    run(&mut apps, &|apps| apps.iter().all(heard_global));
+
+    let heard: Vec<Channel> = apps[1].world().resource::<ReceivedChat>().0
+        .iter()
+        .map(|m| m.channel.clone())
+        .collect();
+    assert_eq!(heard, vec![Channel::Global]);
+
+    let said = &apps[0].world().resource::<ReceivedChat>().0;
+    assert_eq!(said.len(), 2);
+    assert_eq!(said[0].channel, Channel::Local);
+}
+
+#[test]
+fn reconnecting_replaces_the_old_connection() {
+    let server = MockServer::start("127.0.0.1:0").unwrap();
+    let id = server.add_account("iris", "password1");
+    server.add_account("jack", "password1");
+
+    let mut old = connect(&server, "iris");
+    run_until(&mut old, |_| server.connected() == 1);
+
+    let mut watcher = connect(&server, "jack");
+    run_until(&mut watcher, |_| server.connected() == 2);
+
+    let mut new = connect(&server, "iris");
+    run_until(&mut new, |app| !received(app).is_empty());
+
+    // the old connection closes after the new one has taken its place
+    old.world_mut()
+        .resource_mut::<NextState<ViewState>>()
+        .set(ViewState::Menu);
+    old.update();
+
+    let start = Instant::now();
+    run_until(&mut watcher, |_| start.elapsed() > Duration::from_millis(250));
+
+    assert_eq!(server.connected(), 2);
+    assert!(!received(&watcher).iter().any(|m| {
+        matches!(m.value, Value::Disconnect(_)) && m.header.account_id == id
+    }));
+}
@@ -0,0 +1,50 @@
+use std::time::Duration;
+
+use tinker::plugins::network::{backoff, BACKOFF_MAX};
+
+// the longest wait for each attempt, before it's halved by jitter
+const CEILINGS: [(u32, Duration); 9] = [
+    (1, Duration::from_millis(500)),
+    (2, Duration::from_secs(1)),
+    (3, Duration::from_secs(2)),
+    (4, Duration::from_secs(4)),
+    (5, Duration::from_secs(8)),
+    (6, Duration::from_secs(16)),
+    (7, Duration::from_secs(30)),
+    (8, Duration::from_secs(30)),
+    (20, Duration::from_secs(30)),
+];
+
+#[test]
+fn backoff_stays_within_jitter_bounds() {
+    for (attempt, ceiling) in CEILINGS {
+        for _ in 0..100 {
+            let delay = backoff(attempt);
+            assert!(delay >= ceiling / 2, "attempt {} waited {:?}", attempt, delay);
+            assert!(delay <= ceiling, "attempt {} waited {:?}", attempt, delay);
+        }
+    }
+}
+
+#[test]
+fn backoff_grows() {
+    // the shortest wait for one attempt is the longest for the one before
+    for attempt in 1..6 {
+        let longest: Duration = (0..100).map(|_| backoff(attempt)).max().unwrap();
+        let shortest: Duration = (0..100).map(|_| backoff(attempt + 1)).min().unwrap();
+        assert!(shortest >= longest, "attempt {}", attempt);
+    }
+}
+
+#[test]
+fn backoff_is_capped() {
+    assert!(backoff(u32::MAX) <= BACKOFF_MAX);
+    assert!(backoff(1000) >= BACKOFF_MAX / 2);
+    assert!((0..100).map(|_| backoff(30)).all(|d| d <= BACKOFF_MAX));
+}
+
+#[test]
+fn backoff_is_jittered() {
+    let delays: Vec<Duration> = (0..50).map(|_| backoff(4)).collect();
+    assert!(delays.iter().any(|d| *d != delays[0]));
+}
@@ -0,0 +1,247 @@
+use bevy::prelude::*;
+
+use tinker::plugins::pathfinding::Walkability;
+
+// builds a grid from rows of text, where `#` is a blocked tile and a
+// digit is a tile that costs that much to cross
+fn grid(rows: &[&str]) -> Walkability {
+    let mut grid = Walkability::new(
+        rows[0].len() as u32,
+        rows.len() as u32,
+        Vec2::new(256., 128.)
+    );
+
+    for (y, row) in rows.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            let tile = IVec2::new(x as i32, y as i32);
+            grid.set_blocked(tile, c == '#');
+            if let Some(cost) = c.to_digit(10) {
+                grid.set_cost(tile, cost as f32);
+            }
+        }
+    }
+
+    grid
+}
+
+// every step of a path is to a walkable neighbour
+fn assert_connected(grid: &Walkability, path: &[IVec2]) {
+    for pair in path.windows(2) {
+        let d = (pair[1] - pair[0]).abs();
+        assert!(d.x <= 1 && d.y <= 1 && d != IVec2::ZERO, "{:?} jumps", pair);
+        assert!(grid.is_walkable(pair[1]), "{:?} is blocked", pair[1]);
+    }
+}
+
+#[test]
+fn straight_line() {
+    let grid = grid(&[
+        ".....",
+    ]);
+
+    let path = grid.find_path(IVec2::new(0, 0), IVec2::new(4, 0)).unwrap();
+    assert_eq!(path.len(), 5);
+    assert_eq!(path.first(), Some(&IVec2::new(0, 0)));
+    assert_eq!(path.last(), Some(&IVec2::new(4, 0)));
+}
+
+#[test]
+fn start_is_goal() {
+    let grid = grid(&[
+        "...",
+        "...",
+    ]);
+
+    let path = grid.find_path(IVec2::new(1, 1), IVec2::new(1, 1)).unwrap();
+    assert_eq!(path, vec![IVec2::new(1, 1)]);
+}
+
+#[test]
+fn prefers_diagonals() {
+    let grid = grid(&[
+        "....",
+        "....",
+        "....",
+        "....",
+    ]);
+
+    let path = grid.find_path(IVec2::new(0, 0), IVec2::new(3, 3)).unwrap();
+    assert_eq!(path.len(), 4);
+    assert_connected(&grid, &path);
+}
+
+#[test]
+fn around_a_wall() {
+    let grid = grid(&[
+        "..#..",
+        "..#..",
+        "..#..",
+        ".....",
+    ]);
+
+    let path = grid.find_path(IVec2::new(0, 0), IVec2::new(4, 0)).unwrap();
+    assert_connected(&grid, &path);
+    assert!(path.contains(&IVec2::new(2, 3)));
+}
+
+#[test]
+fn no_corner_cutting() {
+    let grid = grid(&[
+        ".#",
+        "#.",
+    ]);
+
+    assert!(grid.find_path(IVec2::new(0, 0), IVec2::new(1, 1)).is_none());
+}
+
+#[test]
+fn unreachable() {
+    let grid = grid(&[
+        "...#.",
+        "...#.",
+        "####.",
+    ]);
+
+    assert!(grid.find_path(IVec2::new(0, 0), IVec2::new(4, 0)).is_none());
+    assert!(grid.find_path(IVec2::new(0, 0), IVec2::new(3, 0)).is_none());
+    assert!(grid.find_path(IVec2::new(0, 0), IVec2::new(9, 9)).is_none());
+}
+
+#[test]
+fn tile_round_trip() {
+    let grid = grid(&[
+        ".....",
+        ".....",
+        ".....",
+        ".....",
+    ]);
+
+    for y in 0..4 {
+        for x in 0..5 {
+            let tile = IVec2::new(x, y);
+            let world = grid.tile_to_world(tile).extend(0.);
+            assert_eq!(grid.world_to_tile(world), tile);
+
+            // anywhere well inside the diamond is still the same tile
+            let nudged = world + Vec3::new(50., 20., 0.);
+            assert_eq!(grid.world_to_tile(nudged), tile);
+        }
+    }
+}
+
+#[test]
+fn isometric_layout() {
+    let grid = grid(&[
+        "..",
+        "..",
+    ]);
+
+    // the map is centred on the origin, with the first tile at the top
+    let top = grid.tile_to_world(IVec2::new(0, 0));
+    assert_eq!(top, Vec2::new(0., 64.));
+
+    // moving along x goes down and right, along y down and left
+    assert_eq!(grid.tile_to_world(IVec2::new(1, 0)), Vec2::new(128., 0.));
+    assert_eq!(grid.tile_to_world(IVec2::new(0, 1)), Vec2::new(-128., 0.));
+    assert_eq!(grid.tile_to_world(IVec2::new(1, 1)), Vec2::new(0., -64.));
+
+    assert!(!grid.contains(grid.world_to_tile(Vec3::new(0., 200., 0.))));
+}
+
+#[test]
+fn avoids_costly_tiles() {
+    let grid = grid(&[
+        ".....",
+        ".999.",
+        ".....",
+    ]);
+
+    let path = grid.find_path(IVec2::new(0, 1), IVec2::new(4, 1)).unwrap();
+    assert_connected(&grid, &path);
+    assert!(path.iter().all(|&tile| grid.cost(tile) == Some(1.)));
+}
+
+#[test]
+fn crosses_costly_tiles_if_it_must() {
+    let grid = grid(&[
+        "#####",
+        ".222.",
+        "#####",
+    ]);
+
+    let path = grid.find_path(IVec2::new(0, 1), IVec2::new(4, 1)).unwrap();
+    assert_eq!(path.len(), 5);
+}
+
+#[test]
+fn costs() {
+    let grid = grid(&[
+        ".3#",
+    ]);
+
+    assert_eq!(grid.cost(IVec2::new(0, 0)), Some(1.));
+    assert_eq!(grid.cost(IVec2::new(1, 0)), Some(3.));
+    assert_eq!(grid.cost(IVec2::new(2, 0)), None);
+    assert_eq!(grid.cost(IVec2::new(3, 0)), None);
+}
+
+#[test]
+fn nearest_to_a_blocked_goal() {
+    let grid = grid(&[
+        "...#.",
+        "...#.",
+        "####.",
+    ]);
+
+    let path = grid.find_nearest_path(IVec2::new(0, 0), IVec2::new(4, 0)).unwrap();
+    assert_connected(&grid, &path);
+    assert_eq!(path.last(), Some(&IVec2::new(2, 0)));
+}
+
+#[test]
+fn nearest_to_a_goal_off_the_map() {
+    let grid = grid(&[
+        "...",
+        "...",
+        "...",
+    ]);
+
+    let path = grid.find_nearest_path(IVec2::new(0, 0), IVec2::new(10, 1)).unwrap();
+    assert_connected(&grid, &path);
+    assert_eq!(path.last(), Some(&IVec2::new(2, 1)));
+}
+
+#[test]
+fn leaves_a_blocked_tile() {
+    let grid = grid(&[
+        "#..",
+    ]);
+
+    let path = grid.find_path(IVec2::new(0, 0), IVec2::new(2, 0)).unwrap();
+    assert_eq!(path.len(), 3);
+}
+
+#[test]
+fn from_tiled_map() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/walkability.tmx");
+    let map = tiled::Loader::new().load_tmx_map(path).unwrap();
+    let grid = Walkability::from_map(&map);
+
+    assert_eq!(grid.size(), UVec2::new(4, 4));
+
+    // open ground
+    assert_eq!(grid.cost(IVec2::new(0, 0)), Some(1.));
+
+    // a tile marked `walkable = false` in the tileset
+    assert!(!grid.is_walkable(IVec2::new(1, 1)));
+
+    // a tile with a `cost` property
+    assert_eq!(grid.cost(IVec2::new(2, 2)), Some(2.));
+
+    // under the rectangle on the collision layer
+    assert!(!grid.is_walkable(IVec2::new(0, 3)));
+    assert!(grid.is_walkable(IVec2::new(1, 3)));
+
+    // nothing drawn here
+    assert!(!grid.is_walkable(IVec2::new(3, 3)));
+}
@@ -0,0 +1,101 @@
+use bevy::math::Vec3;
+
+use tinker::plugins::movement::step;
+use tinker::plugins::prediction::{Prediction, CORRECTION_THRESHOLD};
+
+const TARGET: Vec3 = Vec3::new(100., 0., 0.);
+const SPEED: f32 = 50.;
+const DELTA: f32 = 0.1;
+
+fn x(value: f32) -> Vec3 {
+    Vec3::new(value, 0., 0.)
+}
+
+// four ticks toward `TARGET`, five units each, with a `Move` sent after the second
+fn walked() -> Prediction {
+    let mut prediction = Prediction::default();
+    let mut position = Vec3::ZERO;
+
+    for tick in 0..4 {
+        position = step(position, TARGET, SPEED, DELTA);
+        prediction.record(position, TARGET, SPEED, DELTA);
+        if tick == 1 {
+            prediction.mark_sent();
+        }
+    }
+
+    assert_eq!(position, x(20.));
+    prediction
+}
+
+#[test]
+fn sequences_count_up() {
+    let mut prediction = Prediction::default();
+    assert_eq!(prediction.record(x(1.), TARGET, SPEED, DELTA).sequence, 0);
+    assert_eq!(prediction.record(x(2.), TARGET, SPEED, DELTA).sequence, 1);
+}
+
+#[test]
+fn agreeing_with_a_predicted_position() {
+    // the server is behind, but where it has the player was predicted
+    assert_eq!(walked().acknowledge(x(10.)), None);
+    assert_eq!(walked().acknowledge(x(10. + CORRECTION_THRESHOLD)), None);
+}
+
+#[test]
+fn unacknowledged_inputs_are_replayed() {
+    let mut prediction = walked();
+
+    // the inputs after the `Move` was sent are replayed from the server's position
+    let server = Vec3::new(0., 50., 0.);
+    let expected = step(step(server, TARGET, SPEED, DELTA), TARGET, SPEED, DELTA);
+
+    let reconciled = prediction.acknowledge(server).unwrap();
+    assert!(reconciled.abs_diff_eq(expected, 1e-4));
+}
+
+#[test]
+fn beyond_the_threshold_is_corrected() {
+    let mut prediction = walked();
+
+    // halfway between two predicted positions, too far from either
+    let reconciled = prediction.acknowledge(x(12.5)).unwrap();
+    assert!(reconciled.abs_diff_eq(x(22.5), 1e-4));
+}
+
+#[test]
+fn agreement_settles_earlier_inputs() {
+    let mut prediction = walked();
+
+    // only the last two inputs are left after agreeing on the third
+    assert_eq!(prediction.acknowledge(x(15.)), None);
+
+    let server = Vec3::new(0., 50., 0.);
+    let expected = step(server, TARGET, SPEED, DELTA);
+
+    let reconciled = prediction.acknowledge(server).unwrap();
+    assert!(reconciled.abs_diff_eq(expected, 1e-4));
+}
+
+#[test]
+fn inputs_before_the_last_send_are_not_matched() {
+    let mut prediction = walked();
+
+    // back the way the player came, after telling the server about x(20.)
+    let mut position = x(20.);
+    prediction.mark_sent();
+    for _ in 0..2 {
+        position = step(position, Vec3::ZERO, SPEED, DELTA);
+        prediction.record(position, Vec3::ZERO, SPEED, DELTA);
+    }
+
+    // x(5.) was only passed on the way out, so it is a correction
+    let reconciled = prediction.acknowledge(x(5.)).unwrap();
+    assert!(reconciled.abs_diff_eq(Vec3::ZERO, 1e-4));
+}
+
+#[test]
+fn nothing_to_replay() {
+    let mut prediction = Prediction::default();
+    assert_eq!(prediction.acknowledge(x(7.)), Some(x(7.)));
+}
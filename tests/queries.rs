@@ -0,0 +1,84 @@
+use reqwest::StatusCode;
+
+use tinker::errors::Error;
+use tinker::queries::{failure, Endpoint};
+
+#[test]
+fn conflict_on_register() {
+    assert!(matches!(
+        failure(Endpoint::Register, StatusCode::CONFLICT, ""),
+        Error::UsernameTaken));
+
+    // the code is enough without the status
+    assert!(matches!(
+        failure(Endpoint::Login, StatusCode::BAD_REQUEST, r#"{"code": "username_taken"}"#),
+        Error::UsernameTaken));
+}
+
+#[test]
+fn rejected_login() {
+    assert!(matches!(
+        failure(Endpoint::Login, StatusCode::UNAUTHORIZED, ""),
+        Error::BadCredentials));
+
+    // an unknown account is reported the same as a wrong password
+    assert!(matches!(
+        failure(Endpoint::Login, StatusCode::NOT_FOUND, ""),
+        Error::BadCredentials));
+
+    assert!(matches!(
+        failure(Endpoint::Validate, StatusCode::FORBIDDEN, ""),
+        Error::BadCredentials));
+}
+
+#[test]
+fn not_found_on_register() {
+    assert!(matches!(
+        failure(Endpoint::Register, StatusCode::NOT_FOUND, ""),
+        Error::RegisterFailed(reason) if reason == "Not Found"));
+}
+
+#[test]
+fn rate_limited() {
+    assert!(matches!(
+        failure(Endpoint::Login, StatusCode::TOO_MANY_REQUESTS, ""),
+        Error::RateLimited));
+
+    assert!(matches!(
+        failure(Endpoint::Register, StatusCode::BAD_REQUEST, r#"{"code": "rate_limited"}"#),
+        Error::RateLimited));
+}
+
+#[test]
+fn unprocessable_field() {
+    let body = r#"{"code": "invalid", "field": "username", "reason": "Too short"}"#;
+
+    assert!(matches!(
+        failure(Endpoint::Register, StatusCode::UNPROCESSABLE_ENTITY, body),
+        Error::InvalidField { field, reason } if field == "username" && reason == "Too short"));
+}
+
+#[test]
+fn password_mismatch() {
+    assert!(matches!(
+        failure(Endpoint::Register, StatusCode::UNPROCESSABLE_ENTITY, r#"{"code": "password_mismatch"}"#),
+        Error::PasswordMismatch));
+}
+
+#[test]
+fn unparseable_body() {
+    assert!(matches!(
+        failure(Endpoint::Login, StatusCode::INTERNAL_SERVER_ERROR, "<html>oops</html>"),
+        Error::LoginFailed(reason) if reason == "Internal Server Error"));
+
+    assert!(matches!(
+        failure(Endpoint::Register, StatusCode::UNPROCESSABLE_ENTITY, "{\"field\":"),
+        Error::RegisterFailed(reason) if reason == "Unprocessable Entity"));
+}
+
+#[test]
+fn server_unavailable() {
+    assert!(matches!(
+        failure(Endpoint::Login, StatusCode::SERVICE_UNAVAILABLE, ""),
+        Error::ServerUnreachable));
+}
@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use tinker_records::messages::{ConnectMessage, EntityInfo, Header, Message, Value};
+
+use tinker::config::ClientConfig;
+use tinker::player::{AccountId, Experience, Health};
+use tinker::plugins::network::{Incoming, IncomingStats, NetworkPlugin};
+use tinker::plugins::shutdown::ShutdownPlugin;
+use tinker::state::ConnectionState;
+use tinker::stats::{Damaged, LevelUp, StatChange, StatMessage};
+use tinker::views::{game, ViewState};
+
+const ID: i32 = 7;
+
+#[derive(Resource, Default)]
+struct Seen {
+    level_ups: Vec<LevelUp>,
+    damaged: Vec<Damaged>,
+}
+
+fn watch(mut seen: ResMut<Seen>, mut level_ups: EventReader<LevelUp>, mut damaged: EventReader<Damaged>) {
+    seen.level_ups.extend(level_ups.read().copied());
+    seen.damaged.extend(damaged.read().copied());
+}
+
+// the game logic as a headless client runs it, with another character
+// already in the world
+fn app() -> App {
+    let mut app = App::new();
+    app
+        .add_plugins((MinimalPlugins, StatesPlugin, AssetPlugin::default(), ImagePlugin::default()))
+        .init_asset::<TextureAtlasLayout>()
+        .init_resource::<ConnectionState>()
+        .insert_resource(ClientConfig::default())
+        .init_state::<ViewState>()
+        .add_plugins(game::game_logic)
+        .add_plugins((NetworkPlugin, ShutdownPlugin))
+        .init_resource::<Seen>()
+        .add_systems(Last, watch);
+
+    app.world_mut()
+        .resource_mut::<NextState<ViewState>>()
+        .set(ViewState::Game);
+    app.update();
+
+    app.world_mut().send_event(Incoming(Message {
+        header: Header { account_id: ID },
+        value: Value::Connect(ConnectMessage {
+            entity: EntityInfo { id: ID, username: "ivan".into(), x: 0., y: 0. },
+        }),
+    }));
+    app.update();
+    app
+}
+
+fn stats(app: &mut App, change: StatChange) {
+    app.world_mut().send_event(IncomingStats(StatMessage::new(ID, change)));
+    app.update();
+}
+
+// the character's health and experience as (current, maximum) and
+// (current, level, required)
+fn character(app: &mut App) -> ((usize, usize), (usize, usize, Option<usize>)) {
+    let mut query = app.world_mut().query::<(&AccountId, &Health, &Experience)>();
+    let (_, health, experience) = query
+        .iter(app.world())
+        .find(|(id, ..)| id.0 == ID)
+        .expect("character was not spawned");
+
+    (
+        (health.current, health.maximum),
+        (experience.current, experience.level, experience.required),
+    )
+}
+
+#[test]
+fn health_changes() {
+    let mut app = app();
+
+    stats(&mut app, StatChange::Health { current: 60, maximum: 120 });
+
+    assert_eq!(character(&mut app).0, (60, 120));
+
+    let seen = app.world().resource::<Seen>();
+    assert_eq!(seen.damaged.len(), 1);
+    assert_eq!((seen.damaged[0].amount, seen.damaged[0].remaining), (40, 60));
+
+    // healing isn't damage, and can't go past the maximum
+    stats(&mut app, StatChange::Health { current: 500, maximum: 150 });
+
+    assert_eq!(character(&mut app).0, (150, 150));
+    assert_eq!(app.world().resource::<Seen>().damaged.len(), 1);
+}
+
+#[test]
+fn experience_and_levels() {
+    let mut app = app();
+
+    stats(&mut app, StatChange::Experience { gained: 30 });
+    stats(&mut app, StatChange::Experience { gained: 45 });
+
+    assert_eq!(character(&mut app).1, (75, 1, None));
+    assert!(app.world().resource::<Seen>().level_ups.is_empty());
+
+    stats(&mut app, StatChange::Level { level: 2, experience: 5, required: 200 });
+
+    assert_eq!(character(&mut app).1, (5, 2, Some(200)));
+
+    let seen = app.world().resource::<Seen>();
+    assert_eq!(seen.level_ups.len(), 1);
+    assert_eq!(seen.level_ups[0].level, 2);
+}
@@ -0,0 +1,51 @@
+use tinker::validation::{
+    validate_confirmation, validate_password, validate_register, validate_username, Invalid,
+    PASSWORD_MAX, PASSWORD_MIN, USERNAME_MAX, USERNAME_MIN,
+};
+
+#[test]
+fn username() {
+    assert_eq!(validate_username(""), Some(Invalid::UsernameMissing));
+    assert_eq!(validate_username(&"a".repeat(USERNAME_MIN - 1)), Some(Invalid::UsernameLength));
+    assert_eq!(validate_username(&"a".repeat(USERNAME_MAX + 1)), Some(Invalid::UsernameLength));
+    assert_eq!(validate_username("bad name"), Some(Invalid::UsernameCharacters));
+    assert_eq!(validate_username("bad!"), Some(Invalid::UsernameCharacters));
+    assert_eq!(validate_username("né_é"), Some(Invalid::UsernameCharacters));
+
+    assert_eq!(validate_username(&"a".repeat(USERNAME_MIN)), None);
+    assert_eq!(validate_username(&"a".repeat(USERNAME_MAX)), None);
+    assert_eq!(validate_username("good_name-1"), None);
+}
+
+#[test]
+fn password() {
+    assert_eq!(validate_password(""), Some(Invalid::PasswordMissing));
+    assert_eq!(validate_password("abc123"), Some(Invalid::PasswordLength));
+    assert_eq!(validate_password(&format!("a{}", "1".repeat(PASSWORD_MAX))), Some(Invalid::PasswordLength));
+
+    // needs both a letter and a number
+    assert_eq!(validate_password("abcdefgh"), Some(Invalid::PasswordWeak));
+    assert_eq!(validate_password("12345678"), Some(Invalid::PasswordWeak));
+    assert_eq!(validate_password("!!!!!!!!"), Some(Invalid::PasswordWeak));
+
+    assert_eq!(validate_password(&format!("a{}", "1".repeat(PASSWORD_MIN - 1))), None);
+    assert_eq!(validate_password(&format!("a{}", "1".repeat(PASSWORD_MAX - 1))), None);
+}
+
+#[test]
+fn confirmation() {
+    assert_eq!(validate_confirmation("password1", ""), Some(Invalid::PasswordMissing));
+    assert_eq!(validate_confirmation("password1", "password2"), Some(Invalid::PasswordMismatch));
+    assert_eq!(validate_confirmation("password1", "password1"), None);
+}
+
+#[test]
+fn register_form() {
+    let errors = validate_register("", "short", "other");
+    assert_eq!(errors.username, Some(Invalid::UsernameMissing));
+    assert_eq!(errors.password1, Some(Invalid::PasswordLength));
+    assert_eq!(errors.password2, Some(Invalid::PasswordMismatch));
+    assert!(!errors.is_valid());
+
+    assert!(validate_register("alice", "password1", "password1").is_valid());
+}